@@ -7,8 +7,6 @@
 mod agent;
 mod permissions;
 
-pub use agent::*;
-
 //-------------------------------------------------------------------------------------------------
 // Traits
 //-------------------------------------------------------------------------------------------------
@@ -0,0 +1,108 @@
+//! # Blocking
+//!
+//! Synchronous wrappers around chains. See `versa_model::blocking` for how calls are driven.
+
+use crate::{simple_chain, Chain, ChainError};
+use futures::Stream;
+use versa_model::{
+    blocking::{Runtime, StreamIter},
+    Model, Output,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// A blocking wrapper around a `SimpleChain`.
+#[derive(Debug, Clone)]
+pub struct SimpleChain<M>
+where
+    M: Model,
+{
+    inner: simple_chain::SimpleChain<M>,
+    runtime: Runtime,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl<M> SimpleChain<M>
+where
+    M: Model,
+{
+    /// Wraps the given chain.
+    pub fn new(chain: simple_chain::SimpleChain<M>) -> Self {
+        Self::with_runtime(chain, Runtime::new())
+    }
+
+    /// Wraps the given chain, driving its calls on an existing runtime.
+    pub fn with_runtime(chain: simple_chain::SimpleChain<M>, runtime: Runtime) -> Self {
+        Self {
+            inner: chain,
+            runtime,
+        }
+    }
+
+    /// Prompts the model with the given input.
+    pub fn prompt<O>(&self, prompt: impl Into<M::Input>) -> Result<O, ChainError>
+    where
+        O: Output<M>,
+    {
+        self.runtime.block_on(self.inner.prompt(prompt))
+    }
+
+    /// Prompts the model with the given input and configuration.
+    pub fn prompt_with_config<O>(
+        &self,
+        prompt: impl Into<M::Input>,
+        config: M::Config,
+    ) -> Result<O, ChainError>
+    where
+        O: Output<M>,
+    {
+        self.runtime
+            .block_on(self.inner.prompt_with_config(prompt, config))
+    }
+
+    /// Prompts the model with the given input, consuming the resulting stream as an iterator.
+    pub fn stream<S>(&self, prompt: impl Into<M::Input>) -> Result<StreamIter<S>, ChainError>
+    where
+        S: Output<M> + Stream,
+    {
+        let stream = self.prompt(prompt)?;
+        Ok(self.runtime.iter(stream))
+    }
+
+    /// Gets the wrapped async chain.
+    pub fn get_ref(&self) -> &simple_chain::SimpleChain<M> {
+        &self.inner
+    }
+
+    /// Unwraps the async chain.
+    pub fn into_inner(self) -> simple_chain::SimpleChain<M> {
+        self.inner
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl<M> Default for SimpleChain<M>
+where
+    M: Model + Default,
+{
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<M> From<simple_chain::SimpleChain<M>> for SimpleChain<M>
+where
+    M: Model,
+{
+    fn from(chain: simple_chain::SimpleChain<M>) -> Self {
+        Self::new(chain)
+    }
+}
@@ -6,6 +6,8 @@
 //!
 //! For example, a Chain type could apply autoregression to the input of a model.
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod error;
 pub mod simple_chain;
 mod traits;
@@ -81,7 +81,16 @@ where
     where
         O: Output<M>,
     {
-        Ok(O::from_call_with_config(prompt, &self.config.model, config).await?)
+        let call = O::from_call_with_config(prompt, &self.config.model, config);
+
+        // Model call spans are opened inside this one, so they show up as its children.
+        #[cfg(feature = "tracing")]
+        let call = tracing::Instrument::instrument(
+            call,
+            tracing::info_span!("chain.prompt", chain = "simple"),
+        );
+
+        Ok(call.await?)
     }
 }
 
@@ -1,4 +1,7 @@
 use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod prompt_template;
 
 //-------------------------------------------------------------------------------------------------
 // Macros
@@ -8,3 +11,23 @@ use proc_macro::TokenStream;
 pub fn describe(_attr: TokenStream, _item: TokenStream) -> TokenStream {
     todo!("describe")
 }
+
+/// Implements `versa_prompt::PromptTemplate` for a struct whose fields are the variables of the
+/// template given in `#[prompt(...)]`.
+///
+/// The template is given inline with `#[prompt("...")]`, read from a file relative to the crate
+/// root with `#[prompt(path = "...")]`, or given as messages with
+/// `#[prompt(system = "...", user = "...", assistant = "...")]`. It fails to compile if a
+/// variable has no matching field, or if a field is not used by the template and not marked
+/// `#[prompt(skip)]`.
+///
+/// Fields are named as serde serializes them, following `rename`, `rename_all`, `skip` and
+/// `skip_serializing`. Fields with `skip_serializing_if` can only back variables with a default,
+/// and `flatten` is rejected.
+#[proc_macro_derive(PromptTemplate, attributes(prompt))]
+pub fn derive_prompt_template(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    prompt_template::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,728 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::{collections::BTreeSet, fs, path::Path};
+use syn::{
+    ext::IdentExt, meta::ParseNestedMeta, parenthesized, spanned::Spanned, Attribute, Data,
+    DeriveInput, Error, Fields, Ident, LitStr, Result, Token,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// A template given in the `#[prompt(...)]` attribute.
+struct Message {
+    role: Option<Ident>,
+    text: LitStr,
+    source: Source,
+}
+
+/// Where the text of a template comes from.
+enum Source {
+    Inline,
+    File,
+}
+
+/// A field of the struct, named as it serializes.
+struct Field {
+    name: String,
+    span: proc_macro2::Span,
+
+    /// Whether the field is marked `#[prompt(skip)]`.
+    skip: bool,
+
+    /// Whether serde writes the field out at all.
+    serialized: bool,
+
+    /// The `skip_serializing_if` attribute, if serde may leave the field out.
+    optional: Option<proc_macro2::Span>,
+}
+
+/// The variables of a template.
+#[derive(Debug, Default, PartialEq)]
+struct Variables {
+    /// The variables that must be given, outside loops and without a default.
+    required: BTreeSet<String>,
+
+    /// Every variable the template refers to, including inside loops.
+    used: BTreeSet<String>,
+}
+
+/// A block of a template, or the template itself, while scanning.
+#[derive(Default)]
+struct Block<'a> {
+    /// The variable the block opens with, with whether it has a default.
+    opener: Option<(&'a str, bool)>,
+
+    /// Whether the block is a loop.
+    each: bool,
+
+    /// The variables inside the block, with whether they have a default and are in a nested loop.
+    vars: Vec<(&'a str, bool, bool)>,
+}
+
+/// A tag in the template source.
+enum Tag<'a> {
+    Var(&'a str, bool),
+    If(&'a str, bool),
+    Each(&'a str, bool),
+    Else,
+    EndIf,
+    EndEach,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl<'a> Block<'a> {
+    fn open(name: &'a str, has_default: bool, each: bool) -> Self {
+        Self {
+            opener: Some((name, has_default)),
+            each,
+            vars: vec![],
+        }
+    }
+
+    /// Adds a closed block to this one. The opener is outside the block, its contents are in a
+    /// loop if the block is one.
+    fn close(&mut self, block: Block<'a>) {
+        self.vars.extend(
+            block
+                .opener
+                .map(|(name, has_default)| (name, has_default, false)),
+        );
+        self.vars.extend(
+            block
+                .vars
+                .into_iter()
+                .map(|(name, has_default, in_loop)| (name, has_default, in_loop || block.each)),
+        );
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Implements `PromptTemplate` after checking that the variables of the template and the fields
+/// of the struct match.
+pub(crate) fn derive(input: DeriveInput) -> Result<TokenStream> {
+    let messages = parse_messages(&input)?;
+    let fields = parse_fields(&input)?;
+
+    let mut errors = vec![];
+    let mut used = BTreeSet::new();
+    for message in messages.iter() {
+        let variables = match &message.source {
+            Source::Inline => scan(&message.text.value()),
+            Source::File => scan(&read_file(&message.text)?),
+        };
+
+        for name in variables.required.iter() {
+            match fields.iter().find(|field| &field.name == name) {
+                Some(field) if !field.serialized => errors.push(Error::new(
+                    field.span,
+                    format!("field `{name}` is used by the template but skipped by serde"),
+                )),
+                Some(Field {
+                    optional: Some(span),
+                    ..
+                }) => errors.push(Error::new(
+                    *span,
+                    format!(
+                        "field `{name}` may be left out by `skip_serializing_if`, give the \
+                         template variable a default"
+                    ),
+                )),
+                Some(_) => {}
+                None => errors.push(Error::new(
+                    message.text.span(),
+                    format!("template variable `{name}` has no matching field"),
+                )),
+            }
+        }
+
+        used.extend(variables.used);
+    }
+
+    for field in fields
+        .iter()
+        .filter(|field| field.serialized && !field.skip)
+    {
+        if !used.contains(&field.name) {
+            errors.push(Error::new(
+                field.span,
+                format!(
+                    "field `{}` is not used by the template, mark it `#[prompt(skip)]` to keep it",
+                    field.name
+                ),
+            ));
+        }
+    }
+
+    if let Some(mut error) = errors.pop() {
+        for other in errors {
+            error.combine(other);
+        }
+
+        return Err(error);
+    }
+
+    let texts: Vec<_> = messages
+        .iter()
+        .map(|message| {
+            let text = &message.text;
+            match message.source {
+                Source::Inline => quote!(#text),
+                Source::File => {
+                    quote!(include_str!(
+                        concat!(env!("CARGO_MANIFEST_DIR"), "/", #text)
+                    ))
+                }
+            }
+        })
+        .collect();
+
+    let (prompt, body) = match messages.first() {
+        Some(Message { role: None, .. }) => {
+            let text = &texts[0];
+            (
+                quote!(::versa_prompt::Prompt),
+                quote!(::versa_prompt::Prompt::new(#text)),
+            )
+        }
+        _ => {
+            let roles = messages.iter().map(|message| {
+                let role = Ident::new(
+                    &capitalize(&message.role.as_ref().unwrap().to_string()),
+                    message.role.span(),
+                );
+                quote!(vec![::versa_prompt::Tag::Role(::versa_prompt::Role::#role)])
+            });
+
+            (
+                quote!(::versa_prompt::PromptList),
+                quote! {{
+                    let mut prompt = ::versa_prompt::PromptList::default();
+                    #( prompt.add_message(#texts, #roles); )*
+                    prompt
+                }},
+            )
+        }
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::versa_prompt::PromptTemplate for #name #type_generics #where_clause {
+            type Prompt = #prompt;
+
+            fn template() -> Self::Prompt {
+                #body
+            }
+        }
+    })
+}
+
+/// Parses `#[prompt("...")]`, `#[prompt(path = "...")]` or `#[prompt(system = "...", ...)]`.
+fn parse_messages(input: &DeriveInput) -> Result<Vec<Message>> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("prompt"))
+        .ok_or_else(|| {
+            Error::new(
+                input.ident.span(),
+                "expected a `#[prompt(...)]` attribute with the template",
+            )
+        })?;
+
+    if let Ok(text) = attr.parse_args::<LitStr>() {
+        return Ok(vec![Message {
+            role: None,
+            text,
+            source: Source::Inline,
+        }]);
+    }
+
+    let mut messages = vec![];
+    attr.parse_nested_meta(|meta| {
+        let text: LitStr = meta.value()?.parse()?;
+        if meta.path.is_ident("path") {
+            messages.push(Message {
+                role: None,
+                text,
+                source: Source::File,
+            });
+        } else if ["system", "user", "assistant"]
+            .iter()
+            .any(|role| meta.path.is_ident(role))
+        {
+            messages.push(Message {
+                role: meta.path.get_ident().cloned(),
+                text,
+                source: Source::Inline,
+            });
+        } else {
+            return Err(meta.error("expected `path`, `system`, `user` or `assistant`"));
+        }
+
+        Ok(())
+    })?;
+
+    let single = messages.iter().any(|message| message.role.is_none());
+    if messages.is_empty() || (single && messages.len() > 1) {
+        return Err(Error::new(
+            attr.span(),
+            "expected either a single template or a list of `system`, `user` and `assistant` \
+             messages",
+        ));
+    }
+
+    Ok(messages)
+}
+
+/// Parses the named fields, naming them as serde serializes them and taking `#[prompt(skip)]` into
+/// account. Serde attributes that make the keys unknowable, like `flatten`, are rejected.
+fn parse_fields(input: &DeriveInput) -> Result<Vec<Field>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new(input.ident.span(), "expected named fields")),
+        },
+        _ => return Err(Error::new(input.ident.span(), "expected a struct")),
+    };
+
+    let mut rename_all = None;
+    parse_serde(&input.attrs, |meta| {
+        if meta.path.is_ident("rename_all") {
+            rename_all = serialize_name(&meta)?;
+        } else {
+            skip_meta(&meta)?;
+        }
+
+        Ok(())
+    })?;
+
+    let mut parsed = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let mut name = ident.unraw().to_string();
+        if let Some(rule) = &rename_all {
+            name = rename(rule, &name)?;
+        }
+
+        let mut skip = false;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("prompt"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip`"))
+                }
+            })?;
+        }
+
+        let mut serialized = true;
+        let mut optional = None;
+        parse_serde(&field.attrs, |meta| {
+            if meta.path.is_ident("rename") {
+                if let Some(rename) = serialize_name(&meta)? {
+                    name = rename.value();
+                }
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                serialized = false;
+            } else if meta.path.is_ident("skip_serializing_if") {
+                optional = Some(meta.path.span());
+                skip_meta(&meta)?;
+            } else if meta.path.is_ident("flatten") {
+                return Err(meta.error(
+                    "`#[serde(flatten)]` is not supported by `PromptTemplate`, as the keys of \
+                     the flattened field are not known",
+                ));
+            } else {
+                skip_meta(&meta)?;
+            }
+
+            Ok(())
+        })?;
+
+        parsed.push(Field {
+            name,
+            span: ident.span(),
+            skip,
+            serialized,
+            optional,
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// Calls `f` for every item of the `#[serde(...)]` attributes.
+fn parse_serde(
+    attrs: &[Attribute],
+    mut f: impl FnMut(ParseNestedMeta) -> Result<()>,
+) -> Result<()> {
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(&mut f)?;
+    }
+
+    Ok(())
+}
+
+/// Parses `name = "..."` or `name(serialize = "...", deserialize = "...")`, returning the name
+/// used when serializing.
+fn serialize_name(meta: &ParseNestedMeta) -> Result<Option<LitStr>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse()?));
+    }
+
+    let mut name = None;
+    meta.parse_nested_meta(|meta| {
+        let value: LitStr = meta.value()?.parse()?;
+        if meta.path.is_ident("serialize") {
+            name = Some(value);
+        }
+
+        Ok(())
+    })?;
+
+    Ok(name)
+}
+
+/// Skips the value or arguments of a serde attribute that does not change the keys.
+fn skip_meta(meta: &ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in meta.input);
+        content.parse::<TokenStream>()?;
+    }
+
+    Ok(())
+}
+
+/// Renames a field like `#[serde(rename_all = "...")]` does.
+fn rename(rule: &LitStr, field: &str) -> Result<String> {
+    let pascal = || field.split('_').map(capitalize).collect::<String>();
+
+    Ok(match rule.value().as_str() {
+        "lowercase" | "snake_case" => field.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_ascii_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().chain(chars).collect(),
+                None => pascal,
+            }
+        }
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.replace('_', "-").to_ascii_uppercase(),
+        _ => return Err(Error::new(rule.span(), "unknown `rename_all` rule")),
+    })
+}
+
+/// Reads a template file relative to the crate root.
+fn read_file(path: &LitStr) -> Result<String> {
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    fs::read_to_string(Path::new(&root).join(path.value())).map_err(|error| {
+        Error::new(
+            path.span(),
+            format!("cannot read template `{}`: {error}", path.value()),
+        )
+    })
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Finds the variables of a template, following the same syntax as the runtime parser.
+fn scan(source: &str) -> Variables {
+    // The template itself is at the bottom of the stack and never closes.
+    let mut blocks = vec![Block::default()];
+    let mut rest = source;
+    while let Some(start) = rest.find(['\\', '{']) {
+        rest = &rest[start..];
+
+        // An odd number of backslashes makes the following braces literal.
+        let backslashes = rest.len() - rest.trim_start_matches('\\').len();
+        if backslashes > 0 {
+            let after = &rest[backslashes..];
+            rest = if after.starts_with("{{") && backslashes % 2 == 1 {
+                &after[2..]
+            } else {
+                after
+            };
+            continue;
+        }
+
+        let (tag, len) = match scan_tag(rest) {
+            Some(tag) => tag,
+            None => {
+                rest = &rest[1..];
+                continue;
+            }
+        };
+
+        rest = &rest[len..];
+        let current = blocks.len() - 1;
+        match tag {
+            Tag::Var(name, has_default) => blocks[current].vars.push((name, has_default, false)),
+            Tag::If(name, has_default) => blocks.push(Block::open(name, has_default, false)),
+            Tag::Each(name, has_default) => blocks.push(Block::open(name, has_default, true)),
+            Tag::Else => {}
+            // An end tag that does not match the innermost block is literal text.
+            Tag::EndIf | Tag::EndEach => {
+                if current > 0 && blocks[current].each == matches!(tag, Tag::EndEach) {
+                    let block = blocks.pop().unwrap_or_default();
+                    blocks[current - 1].close(block);
+                }
+            }
+        }
+    }
+
+    // Blocks that are never closed are literal text, but their contents are still parsed.
+    while blocks.len() > 1 {
+        let block = blocks.pop().unwrap_or_default();
+        let parent = blocks.len() - 1;
+        blocks[parent].vars.extend(block.vars);
+    }
+
+    let mut variables = Variables::default();
+    for (name, has_default, in_loop) in blocks.pop().unwrap_or_default().vars {
+        if name == "this" {
+            continue;
+        }
+
+        // Inside loops, variables may refer to fields of the items.
+        if !in_loop && !has_default {
+            variables.required.insert(name.to_string());
+        }
+
+        variables.used.insert(name.to_string());
+    }
+
+    variables
+}
+
+fn scan_tag(text: &str) -> Option<(Tag<'_>, usize)> {
+    let inner = text.strip_prefix("{{")?;
+    for (keyword, tag) in [
+        ("else}}", Tag::Else),
+        ("/if}}", Tag::EndIf),
+        ("/each}}", Tag::EndEach),
+    ] {
+        if inner.starts_with(keyword) {
+            return Some((tag, keyword.len() + 2));
+        }
+    }
+
+    let (tag, rest) = if let Some(rest) = inner.strip_prefix("#if ") {
+        let (name, has_default, rest) = scan_expr(rest.trim_start())?;
+        (Tag::If(name, has_default), rest)
+    } else if let Some(rest) = inner.strip_prefix("#each ") {
+        let (name, has_default, rest) = scan_expr(rest.trim_start())?;
+        (Tag::Each(name, has_default), rest)
+    } else {
+        let (name, has_default, rest) = scan_expr(inner)?;
+        (Tag::Var(name, has_default), rest)
+    };
+
+    rest.starts_with("}}")
+        .then(|| (tag, text.len() - rest.len() + 2))
+}
+
+/// Scans a variable, its path and its filters, returning the variable, whether it has a default
+/// and the rest of the text.
+fn scan_expr(text: &str) -> Option<(&str, bool, &str)> {
+    let (name, mut rest) = scan_name(text)?;
+    loop {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            rest = match scan_index(after_dot) {
+                Some(after_index) => after_index,
+                None => scan_name(after_dot)?.1,
+            };
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            rest = scan_index(after_bracket)?.strip_prefix(']')?;
+        } else {
+            break;
+        }
+    }
+
+    let mut has_default = false;
+    while let Some(after_pipe) = rest.trim_start().strip_prefix('|') {
+        let (filter, after_name) = scan_name(after_pipe.trim_start())?;
+        has_default |= filter == "default";
+        rest = match after_name.trim_start().strip_prefix(':') {
+            Some(after_colon) => scan_arg(after_colon.trim_start())?,
+            None => after_name,
+        };
+    }
+
+    Some((name, has_default, rest.trim_start()))
+}
+
+fn scan_name(text: &str) -> Option<(&str, &str)> {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return None;
+    }
+
+    let len = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+
+    Some(text.split_at(len))
+}
+
+fn scan_index(text: &str) -> Option<&str> {
+    let len = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+
+    (len > 0).then(|| &text[len..])
+}
+
+/// Scans a filter argument, which is an unsigned integer, a boolean or a string.
+fn scan_arg(text: &str) -> Option<&str> {
+    if let Some((word, rest)) = scan_name(text) {
+        return matches!(word, "true" | "false").then_some(rest);
+    }
+
+    if let Some(quoted) = text.strip_prefix('"') {
+        let mut escaped = false;
+        let end = quoted.find(|c: char| {
+            let end = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            end
+        })?;
+
+        return Some(&quoted[end + 1..]);
+    }
+
+    scan_index(text)
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_scans_variables_like_the_runtime_parser() {
+        let variables = scan(
+            "{{user.name | upper}} asks {{question}} in {{tone | default: \"a }} tone\"}}.\n\
+             {{#if examples}}{{#each examples}}- {{input}}: {{this}}{{/each}}{{/if}}\n\
+             \\{{escaped}} {{ spaced }} {{{braced}}",
+        );
+
+        assert_eq!(
+            variables,
+            Variables {
+                required: names(&["braced", "examples", "question", "user"]),
+                used: names(&["braced", "examples", "input", "question", "tone", "user"]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_scans_the_shared_template_cases() {
+        // The same cases are checked against the runtime parser in versa-prompt.
+        let cases = include_str!("../../../versa-prompt/src/template_cases.txt");
+        for line in cases.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut columns = line.split('\t');
+            let source = columns.next().unwrap().replace("\\n", "\n");
+            let required = columns.next().unwrap_or_default();
+            let used = columns.next().unwrap_or_default();
+            assert_eq!(
+                scan(&source),
+                Variables {
+                    required: required.split_whitespace().map(String::from).collect(),
+                    used: used.split_whitespace().map(String::from).collect(),
+                },
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_checks_fields_against_the_template() {
+        let input = syn::parse_quote! {
+            #[prompt(system = "Answer in {{language}}.", user = "{{question}}")]
+            struct Question {
+                #[serde(rename = "language")]
+                lang: String,
+                unused: String,
+                #[prompt(skip)]
+                id: u64,
+            }
+        };
+
+        let error = derive(input).unwrap_err().to_string();
+        assert!(error.contains("field `unused` is not used"), "{error}");
+
+        let input = syn::parse_quote! {
+            #[prompt("{{name}} {{age}}")]
+            struct Person {
+                name: String,
+            }
+        };
+
+        let error = derive(input).unwrap_err().to_string();
+        assert!(error.contains("template variable `age` has no matching field"));
+    }
+
+    #[test]
+    fn test_names_fields_as_serde_does() {
+        let input = syn::parse_quote! {
+            #[prompt("{{userName}} {{Topic}} {{type}} {{note | default: \"none\"}}")]
+            #[serde(rename_all = "camelCase")]
+            struct Request {
+                user_name: String,
+                #[serde(rename(serialize = "Topic", deserialize = "topic"))]
+                topic: String,
+                r#type: String,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                note: Option<String>,
+                #[serde(skip)]
+                cache: u64,
+            }
+        };
+        derive(input).unwrap();
+
+        for (rule, name) in [
+            ("PascalCase", "UserName"),
+            ("SCREAMING_SNAKE_CASE", "USER_NAME"),
+            ("kebab-case", "user-name"),
+            ("SCREAMING-KEBAB-CASE", "USER-NAME"),
+        ] {
+            let rule = LitStr::new(rule, proc_macro2::Span::call_site());
+            assert_eq!(rename(&rule, "user_name").unwrap(), name);
+        }
+    }
+}
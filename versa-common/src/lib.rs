@@ -3,5 +3,5 @@ pub mod traits;
 pub mod utils;
 
 #[cfg(feature = "derive")]
-pub use inner_macros::describe;
+pub use inner_macros::{describe, PromptTemplate};
 pub use utils::Env;
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use futures_util::StreamExt;
+use versa_common::{utils, Env};
+use versa_model::{
+    openai::{
+        OpenAIModel, OpenAISpeechModel, OpenAITranscriptionModel, SpeechStream, Transcription,
+        UploadFile, Voice,
+    },
+    Model,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Main
+//-------------------------------------------------------------------------------------------------
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    utils::load_env(Env::Prod);
+    env_logger::init();
+
+    let model = OpenAISpeechModel::default().voice(Voice::Nova);
+    let audio: Vec<u8> = model
+        .prompt("Versa lets you transcribe a recording and then summarize it.")
+        .await?;
+
+    tokio::fs::write("speech.mp3", &audio).await?;
+
+    let mut stream: SpeechStream = model.prompt("This one is streamed.").await?;
+    while let Some(chunk) = stream.next().await {
+        println!("received {} bytes", chunk?.len());
+    }
+
+    let model = OpenAITranscriptionModel::default().language("en");
+    let transcription: Transcription = model
+        .prompt(UploadFile::from_path("speech.mp3").await?)
+        .await?;
+
+    for segment in transcription.segments.iter() {
+        println!(
+            "[{:.2}s - {:.2}s] {}",
+            segment.start, segment.end, segment.text
+        );
+    }
+
+    let model = OpenAIModel::default();
+    let summary: String = model
+        .prompt(format!("Summarize in five words: {}", transcription.text))
+        .await?;
+
+    println!("summary = {summary:#?}");
+
+    Ok(())
+}
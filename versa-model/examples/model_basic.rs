@@ -14,12 +14,12 @@ async fn main() -> Result<()> {
     utils::load_env(Env::Prod);
     env_logger::init();
 
-    let model = OpenAIModel::default();
+    let model = OpenAIModel::from_env()?;
     let output: String = model.prompt("Hello there!").await?;
 
     println!("chat model output = {output:#?}");
 
-    let model = OpenAICompletionModel::default();
+    let model = OpenAICompletionModel::from_env()?;
     let output: String = model.prompt("Hello there!").await?;
 
     println!("completion model output = {output:#?}");
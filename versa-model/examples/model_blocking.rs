@@ -0,0 +1,28 @@
+use anyhow::Result;
+use versa_common::{utils, Env};
+use versa_model::{
+    blocking,
+    openai::{ChatModelStream, OpenAIChatModel},
+};
+
+//-------------------------------------------------------------------------------------------------
+// Main
+//-------------------------------------------------------------------------------------------------
+
+fn main() -> Result<()> {
+    utils::load_env(Env::Prod);
+    env_logger::init();
+
+    let model = blocking::OpenAI::new(OpenAIChatModel::default());
+    let output: String = model.prompt("Hello there!")?;
+
+    println!("chat model output = {output:#?}");
+
+    for chunk in model.stream::<ChatModelStream>("Tell me a short story.")? {
+        print!("{}", chunk?);
+    }
+
+    println!();
+
+    Ok(())
+}
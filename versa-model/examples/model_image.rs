@@ -0,0 +1,31 @@
+use anyhow::Result;
+use versa_common::{utils, Env};
+use versa_model::{
+    openai::{GeneratedImage, ImageModel, ImageSize, OpenAIImageModel},
+    Model,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Main
+//-------------------------------------------------------------------------------------------------
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    utils::load_env(Env::Prod);
+    env_logger::init();
+
+    let model = OpenAIImageModel::default()
+        .model(ImageModel::DallE2)
+        .size(ImageSize::Size256x256);
+
+    let urls: Vec<String> = model.prompt("A watercolor fox in the snow").await?;
+
+    println!("image urls = {urls:#?}");
+
+    let images: Vec<GeneratedImage> = model.prompt("A watercolor fox in the snow").await?;
+    images[0].save("fox.png").await?;
+
+    println!("image saved to fox.png");
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use versa_common::{utils, Env};
+use versa_model::{
+    openai::{ChatModelResponse, CompletionModelResponse, OpenAICompletionModel, OpenAIModel},
+    Model,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Main
+//-------------------------------------------------------------------------------------------------
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    utils::load_env(Env::Prod);
+    env_logger::init();
+
+    let prompt = "Classify the sentiment of \"I was not happy with the service.\" as one word.";
+
+    let model = OpenAIModel::default().logprobs(true).top_logprobs(3);
+    let response: ChatModelResponse = model.prompt(prompt).await?;
+
+    if let Some(logprobs) = &response.choices[0].logprobs {
+        for token in logprobs.iter() {
+            println!(
+                "chat token = {:?}, p = {}",
+                token.token,
+                token.probability()
+            );
+        }
+    }
+
+    let model = OpenAICompletionModel::default().logprobs(3);
+    let response: CompletionModelResponse = model.prompt(prompt).await?;
+
+    if let Some(logprobs) = &response.choices[0].logprobs {
+        println!("completion confidence = {}", logprobs.probability());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use versa_common::{utils, Env};
+use versa_model::{
+    openai::{ModerationModel, ModerationResult, OpenAIChatModel, OpenAIModerationModel},
+    Model, ModelError,
+};
+use versa_prompt::{prompt, FinalizablePrompt};
+
+//-------------------------------------------------------------------------------------------------
+// Main
+//-------------------------------------------------------------------------------------------------
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    utils::load_env(Env::Prod);
+    env_logger::init();
+
+    let model = OpenAIModerationModel::default();
+    let result: ModerationResult = model.prompt("I want to hurt someone").await?;
+    for category in result.categories.flagged() {
+        println!("{category}: {:.3}", result.category_scores.get(&category));
+    }
+
+    let model = OpenAIChatModel::default().moderation(ModerationModel::TextModerationLatest);
+    let prompt = prompt!(
+        system: "You are a helpful assistant.",
+        user: "I want to hurt someone"
+    );
+
+    match model.prompt::<String>(prompt.finalize()?).await {
+        Ok(output) => println!("output: {output}"),
+        Err(ModelError::Flagged(categories)) => println!("flagged: {categories:?}"),
+        Err(error) => return Err(error.into()),
+    }
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
 use anyhow::Result;
 use futures_util::StreamExt;
+use std::{io::Write, time::Duration};
 use versa_common::{utils, Env};
 use versa_model::{
-    openai::{ChatModelStream, OpenAIModel},
+    openai::{ChatModelStream, OpenAIModel, TextStreamExt},
     Model,
 };
 
@@ -22,5 +23,26 @@ async fn main() -> Result<()> {
         println!("word = {:#?}", output?);
     }
 
+    let stream: ChatModelStream = model.prompt("Write a short poem about the sea.").await?;
+    let mut lines = stream
+        .idle_timeout(Duration::from_secs(10))
+        .lines()
+        .boxed_local();
+
+    while let Some(line) = lines.next().await {
+        print!("line = {}", line?);
+    }
+
+    let stream: ChatModelStream = model.prompt("Count from 1 to 20.").await?;
+    let output = stream
+        .stop_at("10")
+        .collect_tee(|chunk| {
+            print!("{chunk}");
+            std::io::stdout().flush().ok();
+        })
+        .await?;
+
+    println!("\ncollected = {output:#?}");
+
     Ok(())
 }
@@ -0,0 +1,36 @@
+use anyhow::Result;
+use versa_common::{utils, Env};
+use versa_model::{
+    openai::{ChatModel, OpenAIModel},
+    Model,
+};
+use versa_prompt::{prompt, FinalizablePrompt, Image, ImageDetail};
+
+//-------------------------------------------------------------------------------------------------
+// Main
+//-------------------------------------------------------------------------------------------------
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    utils::load_env(Env::Prod);
+    env_logger::init();
+
+    let prompt = prompt!(
+        system: "You describe images in one sentence.",
+        user: {
+            "What is in this image?",
+            Image::url("https://upload.wikimedia.org/wikipedia/commons/3/3a/Cat03.jpg")
+                .detail(ImageDetail::Low)
+        }
+    );
+
+    let model = OpenAIModel::default()
+        .model(ChatModel::Custom("gpt-4-vision-preview".into()))
+        .max_tokens(100);
+
+    let output: String = model.prompt(prompt.finalize()?).await?;
+
+    println!("vision model output = {output:#?}");
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+//! # Blocking
+//!
+//! Synchronous wrappers for code that does not run inside an async runtime, such as CLI tools and
+//! build scripts.
+//!
+//! Every wrapper drives its calls on an internal single-threaded runtime. They can be used from any
+//! thread that is not already driving async code, including the blocking threads of another
+//! runtime (e.g. inside `tokio::task::spawn_blocking`). Calling them from an async task panics, but
+//! they can be created, moved and dropped anywhere.
+
+use crate::{openai, openai::ModelKind, Model, ModelError, Output};
+use futures::{Stream, StreamExt};
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// The runtime that blocking calls are driven on.
+///
+/// Cloning it is cheap, clones share the same runtime. Streams keep their connection registered
+/// with the runtime that opened them, so they hold on to it as well.
+#[derive(Clone)]
+pub struct Runtime {
+    inner: Arc<SharedRuntime>,
+}
+
+/// The runtime shared by the clones of a `Runtime`, shut down in the background when the last
+/// clone is dropped, so that dropping it from an async task does not panic.
+struct SharedRuntime(Option<tokio::runtime::Runtime>);
+
+/// A blocking wrapper around an `OpenAI` model.
+#[derive(Clone)]
+pub struct OpenAI<M>
+where
+    M: ModelKind,
+{
+    inner: openai::OpenAI<M>,
+    runtime: Runtime,
+}
+
+/// A stream consumed as an `Iterator`, one blocking poll per item.
+pub struct StreamIter<S> {
+    inner: Pin<Box<S>>,
+    runtime: Runtime,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl Runtime {
+    /// Creates a new runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime cannot be built, which only happens if the OS refuses to create its
+    /// I/O driver.
+    pub fn new() -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build the blocking runtime");
+
+        Self {
+            inner: Arc::new(SharedRuntime(Some(runtime))),
+        }
+    }
+
+    /// Runs the future to completion, blocking the current thread.
+    pub fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        match &self.inner.0 {
+            Some(runtime) => runtime.block_on(future),
+            None => unreachable!("the runtime is only taken when dropped"),
+        }
+    }
+
+    /// Turns the stream into an iterator driven by this runtime.
+    pub fn iter<S>(&self, stream: S) -> StreamIter<S>
+    where
+        S: Stream,
+    {
+        StreamIter {
+            inner: Box::pin(stream),
+            runtime: self.clone(),
+        }
+    }
+}
+
+impl<M> OpenAI<M>
+where
+    M: ModelKind,
+{
+    /// Wraps the given model.
+    pub fn new(model: openai::OpenAI<M>) -> Self {
+        Self::with_runtime(model, Runtime::new())
+    }
+
+    /// Wraps the given model, driving its calls on an existing runtime.
+    pub fn with_runtime(model: openai::OpenAI<M>, runtime: Runtime) -> Self {
+        Self {
+            inner: model,
+            runtime,
+        }
+    }
+
+    /// Generates output from the given input.
+    pub fn prompt<O>(&self, input: impl Into<M::Input>) -> Result<O, ModelError>
+    where
+        O: Output<openai::OpenAI<M>>,
+    {
+        self.runtime.block_on(self.inner.prompt(input))
+    }
+
+    /// Generates output from the given input and configuration.
+    pub fn prompt_with_config<O>(
+        &self,
+        input: impl Into<M::Input>,
+        config: M::Config,
+    ) -> Result<O, ModelError>
+    where
+        O: Output<openai::OpenAI<M>>,
+    {
+        self.runtime
+            .block_on(self.inner.prompt_with_config(input, config))
+    }
+
+    /// Generates a stream from the given input, consumed as an iterator.
+    ///
+    /// ```no_run
+    /// use versa_model::{blocking, openai::{ChatModelStream, OpenAIChatModel}};
+    ///
+    /// let model = blocking::OpenAI::new(OpenAIChatModel::default());
+    /// for chunk in model.stream::<ChatModelStream>("Hello there!")? {
+    ///     print!("{}", chunk?);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn stream<S>(&self, input: impl Into<M::Input>) -> Result<StreamIter<S>, ModelError>
+    where
+        S: Output<openai::OpenAI<M>> + Stream,
+    {
+        let stream = self.prompt(input)?;
+        Ok(self.runtime.iter(stream))
+    }
+
+    /// Gets the wrapped async model.
+    pub fn get_ref(&self) -> &openai::OpenAI<M> {
+        &self.inner
+    }
+
+    /// Gets the runtime calls are driven on.
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Unwraps the async model.
+    pub fn into_inner(self) -> openai::OpenAI<M> {
+        self.inner
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Runtime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Runtime").finish_non_exhaustive()
+    }
+}
+
+impl<M> Debug for OpenAI<M>
+where
+    M: ModelKind,
+    M::Config: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenAI")
+            .field("inner", &self.inner)
+            .field("runtime", &self.runtime)
+            .finish()
+    }
+}
+
+impl<M> Default for OpenAI<M>
+where
+    M: ModelKind,
+{
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<M> From<openai::OpenAI<M>> for OpenAI<M>
+where
+    M: ModelKind,
+{
+    fn from(model: openai::OpenAI<M>) -> Self {
+        Self::new(model)
+    }
+}
+
+impl Drop for SharedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl<S> Iterator for StreamIter<S>
+where
+    S: Stream,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.inner.next())
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai::{CredentialChain, OpenAIChatModel};
+    use futures::stream;
+
+    #[test]
+    fn test_stream_iter() {
+        let runtime = Runtime::new();
+        let items: Vec<_> = runtime.iter(stream::iter(1..=3)).collect();
+
+        assert_eq!(items, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_from_another_runtimes_blocking_thread() {
+        let output = tokio::task::spawn_blocking(|| {
+            let runtime = Runtime::new();
+            runtime.block_on(async {
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                42
+            })
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(output, 42);
+    }
+
+    #[tokio::test]
+    async fn test_wrapper_can_move_into_spawn_blocking() {
+        let model = OpenAI::new(OpenAIChatModel::default().credentials(CredentialChain::new()));
+        let iter = model.runtime().iter(stream::iter(1..=3));
+
+        let (model, items) = tokio::task::spawn_blocking(move || {
+            let output = model.prompt::<String>("Hello there!");
+            assert!(output.is_err());
+
+            let items: Vec<_> = iter.collect();
+            (model, items)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(items, vec![1, 2, 3]);
+
+        // Dropping the last handle to the runtime from an async task must not panic.
+        drop(model);
+    }
+}
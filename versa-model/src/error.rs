@@ -1,4 +1,4 @@
-use crate::openai::OpenAIError;
+use crate::openai::{ModerationCategory, OpenAIError};
 use thiserror::Error;
 
 //-------------------------------------------------------------------------------------------------
@@ -8,5 +8,31 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum ModelError {
     #[error("openai: {0}")]
-    OpenAI(#[from] OpenAIError),
+    OpenAI(OpenAIError),
+
+    /// The call was cancelled. `partial` holds the text streamed before that, if any.
+    #[error("cancelled")]
+    Cancelled { partial: String },
+
+    /// The call did not finish before its deadline. `partial` holds the text streamed before that,
+    /// if any.
+    #[error("deadline exceeded")]
+    Timeout { partial: String },
+
+    #[error("input flagged by moderation: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Flagged(Vec<ModerationCategory>),
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl From<OpenAIError> for ModelError {
+    fn from(err: OpenAIError) -> Self {
+        match err {
+            OpenAIError::Cancelled { partial } => Self::Cancelled { partial },
+            OpenAIError::Timeout { partial } => Self::Timeout { partial },
+            err => Self::OpenAI(err),
+        }
+    }
 }
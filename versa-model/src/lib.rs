@@ -3,6 +3,8 @@
 //! Models are the core of the application. They provide access to multiple ppopular AI models that
 //! can be used to generate text, image, etc.
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod error;
 pub mod openai;
 mod traits;
@@ -0,0 +1,361 @@
+//! This module contains the implementations of the OpenAI audio models.
+
+use super::{
+    form_value, telemetry::CallSpan, OpenAIConfig, OpenAIError, OpenAISpeechModel,
+    OpenAITranscriptionModel, SpeechConfig, SpeechFormat, SpeechModel, TranscriptionConfig,
+    TranscriptionModel, TranscriptionResponseFormat, UploadFile, Voice,
+};
+use crate::{ModelError, Output};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use pin_project_lite::pin_project;
+use reqwest::{multipart::Form, Client};
+use serde::{Deserialize, Serialize};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct SpeechBody {
+    pub input: String,
+
+    #[serde(flatten)]
+    pub config: SpeechConfig,
+}
+
+/// A transcript in the `verbose_json` format, with segment and word timestamps.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Transcription {
+    pub text: String,
+    pub language: Option<String>,
+    pub duration: Option<f32>,
+
+    #[serde(default)]
+    pub segments: Vec<TranscriptionSegment>,
+
+    #[serde(default)]
+    pub words: Vec<TranscriptionWord>,
+}
+
+/// A segment of a transcript. Times are in seconds.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TranscriptionSegment {
+    pub id: u64,
+    pub seek: u64,
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+    pub tokens: Vec<u64>,
+    pub temperature: f32,
+    pub avg_logprob: f32,
+    pub compression_ratio: f32,
+    pub no_speech_prob: f32,
+}
+
+/// A word of a transcript. Times are in seconds.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TranscriptionWord {
+    pub word: String,
+    pub start: f32,
+    pub end: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptionText {
+    text: String,
+}
+
+pin_project! {
+    /// A stream of synthesized audio bytes.
+    pub struct SpeechStream {
+        #[pin]
+        inner: BoxStream<'static, reqwest::Result<Bytes>>,
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+// TODO(nyprothegeek): Document the builder methods properly.
+impl OpenAITranscriptionModel {
+    /// Sets the model.
+    pub fn model(mut self, model: TranscriptionModel) -> Self {
+        self.config.model = model;
+        self
+    }
+
+    /// Sets the language of the audio as an ISO-639-1 code.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.config.attributes.language = Some(language.into());
+        self
+    }
+
+    /// Sets the text that guides the style of the transcript.
+    ///
+    /// This is not named `prompt` so it does not shadow `Model::prompt`.
+    pub fn prompt_text(mut self, prompt: impl Into<String>) -> Self {
+        self.config.attributes.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Sets the response format.
+    pub fn response_format(mut self, response_format: TranscriptionResponseFormat) -> Self {
+        self.config.attributes.response_format = Some(response_format);
+        self
+    }
+
+    /// Sets the temperature. Must be between 0 and 1.
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.config.attributes.temperature = Some(temperature);
+        self
+    }
+}
+
+// TODO(nyprothegeek): Document the builder methods properly.
+impl OpenAISpeechModel {
+    /// Sets the model.
+    pub fn model(mut self, model: SpeechModel) -> Self {
+        self.config.model = model;
+        self
+    }
+
+    /// Sets the voice.
+    pub fn voice(mut self, voice: Voice) -> Self {
+        self.config.voice = voice;
+        self
+    }
+
+    /// Sets the audio format.
+    pub fn response_format(mut self, response_format: SpeechFormat) -> Self {
+        self.config.attributes.response_format = Some(response_format);
+        self
+    }
+
+    /// Sets the speed. Must be between 0.25 and 4.
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.config.attributes.speed = Some(speed);
+        self
+    }
+}
+
+impl OpenAITranscriptionModel {
+    /// Sends the audio file to the transcription endpoint and returns the raw response body.
+    async fn transcribe(
+        &self,
+        file: UploadFile,
+        config: TranscriptionConfig,
+    ) -> Result<String, OpenAIError> {
+        config.validate()?;
+
+        let attributes = &config.attributes;
+        let mut form = Form::new()
+            .text("model", config.model.to_string())
+            .part("file", file.into_part());
+
+        if let Some(language) = &attributes.language {
+            form = form.text("language", language.clone());
+        }
+
+        if let Some(prompt) = &attributes.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+
+        if let Some(response_format) = attributes.response_format {
+            form = form.text("response_format", form_value(&response_format)?);
+        }
+
+        if let Some(temperature) = attributes.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+
+        let span = CallSpan::new(&config.model, config.get_url(), false);
+        let call = async {
+            let request = Client::new().post(config.get_url()).multipart(form);
+            Ok(self.send_raw(request).await?.text().await?)
+        };
+
+        let result = span.instrument(call).await;
+        span.record_result(&result, |_| None, |_| None);
+
+        result
+    }
+}
+
+impl OpenAISpeechModel {
+    /// Sends the text to the speech endpoint and returns the response.
+    async fn synthesize(
+        &self,
+        input: String,
+        config: SpeechConfig,
+    ) -> Result<reqwest::Response, OpenAIError> {
+        config.validate()?;
+
+        let span = CallSpan::new(&config.model, config.get_url(), false);
+        let url = config.get_url().to_string();
+        let body = SpeechBody { input, config };
+        span.record_request(&body);
+
+        let request = Client::new().post(url).json(&body);
+        let result = span.instrument(self.send_raw(request)).await;
+        span.record_result(&result, |_| None, |_| None);
+
+        result
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+#[async_trait(?Send)]
+impl Output<OpenAITranscriptionModel> for String {
+    /// Returns the transcript text. Subtitle and plain text formats are returned as is.
+    async fn from_call_with_config(
+        input: impl Into<UploadFile>,
+        model: &OpenAITranscriptionModel,
+        config: TranscriptionConfig,
+    ) -> Result<Self, ModelError> {
+        let format = config.attributes.response_format;
+        let body = model.transcribe(input.into(), config).await?;
+
+        Ok(match format {
+            Some(
+                TranscriptionResponseFormat::Text
+                | TranscriptionResponseFormat::Srt
+                | TranscriptionResponseFormat::Vtt,
+            ) => body,
+            _ => {
+                serde_json::from_str::<TranscriptionText>(&body)
+                    .map_err(OpenAIError::SerdeJson)?
+                    .text
+            }
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAITranscriptionModel> for Transcription {
+    async fn from_call_with_config(
+        input: impl Into<UploadFile>,
+        model: &OpenAITranscriptionModel,
+        mut config: TranscriptionConfig,
+    ) -> Result<Self, ModelError> {
+        config.attributes.response_format = Some(TranscriptionResponseFormat::VerboseJson);
+        let body = model.transcribe(input.into(), config).await?;
+
+        Ok(serde_json::from_str(&body).map_err(OpenAIError::SerdeJson)?)
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAISpeechModel> for Vec<u8> {
+    async fn from_call_with_config(
+        input: impl Into<String>,
+        model: &OpenAISpeechModel,
+        config: SpeechConfig,
+    ) -> Result<Self, ModelError> {
+        let response = model.synthesize(input.into(), config).await?;
+        let bytes = response.bytes().await.map_err(OpenAIError::Reqwest)?;
+
+        Ok(bytes.to_vec())
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAISpeechModel> for SpeechStream {
+    async fn from_call_with_config(
+        input: impl Into<String>,
+        model: &OpenAISpeechModel,
+        config: SpeechConfig,
+    ) -> Result<Self, ModelError> {
+        let response = model.synthesize(input.into(), config).await?;
+
+        Ok(SpeechStream {
+            inner: response.bytes_stream().boxed(),
+        })
+    }
+}
+
+impl Stream for SpeechStream {
+    type Item = Result<Bytes, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project()
+            .inner
+            .poll_next(cx)
+            .map_err(OpenAIError::Reqwest)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_deserialize_verbose_transcription() {
+        let transcription: Transcription = serde_json::from_str(
+            r#"{
+                "task": "transcribe",
+                "language": "english",
+                "duration": 2.5,
+                "text": "Hello there.",
+                "segments": [
+                    {
+                        "id": 0,
+                        "seek": 0,
+                        "start": 0.0,
+                        "end": 2.5,
+                        "text": " Hello there.",
+                        "tokens": [50364, 2425, 456, 13],
+                        "temperature": 0.0,
+                        "avg_logprob": -0.3,
+                        "compression_ratio": 0.7,
+                        "no_speech_prob": 0.01
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(transcription.text, "Hello there.");
+        assert_eq!(transcription.segments[0].end, 2.5);
+        assert!(transcription.words.is_empty());
+    }
+
+    #[test]
+    fn test_speech_body_serializes_voice_and_format() {
+        let body = SpeechBody {
+            input: "Hello there.".into(),
+            config: SpeechConfig {
+                voice: Voice::Nova,
+                attributes: crate::openai::SpeechAttributes {
+                    response_format: Some(SpeechFormat::Opus),
+                    speed: Some(1.5),
+                },
+                ..Default::default()
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            serde_json::json!({
+                "input": "Hello there.",
+                "model": "tts-1",
+                "voice": "nova",
+                "response_format": "opus",
+                "speed": 1.5
+            })
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Caching of model responses, so identical deterministic calls are only sent once.
+
+use super::OpenAIError;
+use async_trait::async_trait;
+use lru::LruCache;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Debug,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+//-------------------------------------------------------------------------------------------------
+// Traits
+//-------------------------------------------------------------------------------------------------
+
+/// A store for cached responses.
+#[async_trait]
+pub trait CacheStore: Debug + Send + Sync {
+    /// Gets the entry stored under `key`.
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, OpenAIError>;
+
+    /// Stores `entry` under `key`, replacing any existing entry.
+    async fn put(&self, key: &str, entry: CacheEntry) -> Result<(), OpenAIError>;
+}
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// A cached response body.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// The response, serialized as JSON.
+    pub body: String,
+
+    /// When the entry was stored, in milliseconds since the Unix epoch.
+    pub created: u64,
+}
+
+/// An opt-in response cache for `OpenAI` models.
+///
+/// Requests are keyed by a hash of their serialized body, so any change to the messages or
+/// attributes misses the cache. Only calls with a `temperature` of zero are cached unless the cache
+/// is forced. Streamed calls are replayed from the cache but do not populate it.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    store: Arc<dyn CacheStore>,
+    ttl: Option<Duration>,
+    force: bool,
+}
+
+/// An in-memory store that evicts the least recently used entries.
+#[derive(Debug)]
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, CacheEntry>>,
+}
+
+/// An on-disk store backed by `sled`.
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledCache {
+    db: sled::Db,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl ResponseCache {
+    /// Creates a cache over the given store.
+    pub fn new(store: impl CacheStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            ttl: None,
+            force: false,
+        }
+    }
+
+    /// Creates an in-memory cache holding at most `capacity` responses.
+    pub fn memory(capacity: usize) -> Self {
+        Self::new(MemoryCache::new(capacity))
+    }
+
+    /// Creates an on-disk cache at the given path.
+    #[cfg(feature = "sled")]
+    pub fn sled(path: impl AsRef<std::path::Path>) -> Result<Self, OpenAIError> {
+        Ok(Self::new(SledCache::open(path)?))
+    }
+
+    /// Sets how long entries stay valid. They never expire by default.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Caches calls regardless of their temperature.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Whether a call with the given temperature should go through the cache.
+    pub(crate) fn applies(&self, temperature: Option<f32>) -> bool {
+        // The API defaults to a temperature of 1.
+        self.force || temperature == Some(0.0)
+    }
+
+    /// Returns the cache key of a request body sent to `url`.
+    pub(crate) fn key(url: &str, body: &impl Serialize) -> Result<String, OpenAIError> {
+        // Going through `Value` sorts object keys, so the key does not depend on field order.
+        let body = serde_json::to_value(body)?.to_string();
+        let digest = Sha256::new()
+            .chain_update(url)
+            .chain_update("\n")
+            .chain_update(body)
+            .finalize();
+
+        Ok(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+
+    /// Gets the unexpired response stored under `key`.
+    pub(crate) async fn get<T>(&self, key: &str) -> Result<Option<T>, OpenAIError>
+    where
+        T: DeserializeOwned,
+    {
+        let entry = match self.store.get(key).await? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        if let Some(ttl) = self.ttl {
+            if Duration::from_millis(now().saturating_sub(entry.created)) >= ttl {
+                return Ok(None);
+            }
+        }
+
+        #[cfg(feature = "log")]
+        log::debug!("cache hit: {key}");
+
+        Ok(Some(serde_json::from_str(&entry.body)?))
+    }
+
+    /// Stores `response` under `key`.
+    pub(crate) async fn put<T>(&self, key: &str, response: &T) -> Result<(), OpenAIError>
+    where
+        T: Serialize,
+    {
+        let entry = CacheEntry {
+            body: serde_json::to_string(response)?,
+            created: now(),
+        };
+
+        self.store.put(key, entry).await
+    }
+
+    /// Stores `response` under `key`, logging failures instead of returning them, since the
+    /// response has already been paid for.
+    pub(crate) async fn put_or_log<T>(&self, key: &str, response: &T)
+    where
+        T: Serialize,
+    {
+        if let Err(_error) = self.put(key, response).await {
+            #[cfg(feature = "log")]
+            log::warn!("cannot cache response {key}: {_error}");
+        }
+    }
+}
+
+impl MemoryCache {
+    /// Creates a store holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity.max(1)).unwrap();
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+#[cfg(feature = "sled")]
+impl SledCache {
+    /// Opens or creates the store at the given path.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, OpenAIError> {
+        Ok(Self {
+            db: sled::open(path)?,
+        })
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+#[async_trait]
+impl CacheStore for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, OpenAIError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, entry: CacheEntry) -> Result<(), OpenAIError> {
+        self.entries.lock().unwrap().put(key.to_string(), entry);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sled")]
+#[async_trait]
+impl CacheStore for SledCache {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, OpenAIError> {
+        match self.db.get(key)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, key: &str, entry: CacheEntry) -> Result<(), OpenAIError> {
+        self.db.insert(key, serde_json::to_vec(&entry)?)?;
+        self.db.flush_async().await?;
+        Ok(())
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_key_is_canonical() {
+        let url = "https://api.openai.com/v1/chat/completions";
+        let a = ResponseCache::key(url, &json!({"model": "gpt-4", "temperature": 0.0})).unwrap();
+        let b = ResponseCache::key(url, &json!({"temperature": 0.0, "model": "gpt-4"})).unwrap();
+        let c = ResponseCache::key(url, &json!({"model": "gpt-4", "temperature": 0.5})).unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.len(), 64);
+    }
+
+    #[test]
+    fn test_applies_only_to_deterministic_calls_unless_forced() {
+        let cache = ResponseCache::memory(8);
+        assert!(cache.applies(Some(0.0)));
+        assert!(!cache.applies(Some(0.7)));
+        assert!(!cache.applies(None));
+        assert!(cache.force(true).applies(Some(0.7)));
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_evicts_and_expires() {
+        let cache = ResponseCache::memory(2);
+        cache.put("a", &1).await.unwrap();
+        cache.put("b", &2).await.unwrap();
+        assert_eq!(cache.get::<u8>("a").await.unwrap(), Some(1));
+
+        // "b" is now the least recently used entry.
+        cache.put("c", &3).await.unwrap();
+        assert_eq!(cache.get::<u8>("b").await.unwrap(), None);
+        assert_eq!(cache.get::<u8>("c").await.unwrap(), Some(3));
+
+        // Entries expire with sub-second precision.
+        let cache = cache.ttl(Duration::from_millis(500));
+        assert_eq!(cache.get::<u8>("c").await.unwrap(), Some(3));
+
+        let cache = cache.ttl(Duration::ZERO);
+        assert_eq!(cache.get::<u8>("c").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_writes_are_not_errors() {
+        #[derive(Debug)]
+        struct ReadOnly;
+
+        #[async_trait]
+        impl CacheStore for ReadOnly {
+            async fn get(&self, _: &str) -> Result<Option<CacheEntry>, OpenAIError> {
+                Ok(None)
+            }
+
+            async fn put(&self, _: &str, _: CacheEntry) -> Result<(), OpenAIError> {
+                Err(OpenAIError::Io(std::io::ErrorKind::Other.into()))
+            }
+        }
+
+        let cache = ResponseCache::new(ReadOnly);
+        assert!(cache.put("a", &1).await.is_err());
+        cache.put_or_log("a", &1).await;
+        assert_eq!(cache.get::<u8>("a").await.unwrap(), None);
+    }
+}
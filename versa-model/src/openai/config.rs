@@ -1,6 +1,10 @@
-use super::{ChatModel, CompletionModel};
+use super::{
+    CancellationToken, ChatModel, CompletionModel, ImageModel, ModerationModel, SpeechModel,
+    TranscriptionModel, ValidationError,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+use strum_macros::Display;
 use versa_common::traits::Config;
 
 //-------------------------------------------------------------------------------------------------
@@ -9,13 +13,76 @@ use versa_common::traits::Config;
 
 pub const OPENAI_COMPLETION_URL: &str = "https://api.openai.com/v1/completions";
 pub const OPENAI_CHAT_URL: &str = "https://api.openai.com/v1/chat/completions";
+pub const OPENAI_MODELS_URL: &str = "https://api.openai.com/v1/models";
+pub const OPENAI_IMAGE_GENERATION_URL: &str = "https://api.openai.com/v1/images/generations";
+pub const OPENAI_IMAGE_EDIT_URL: &str = "https://api.openai.com/v1/images/edits";
+pub const OPENAI_IMAGE_VARIATION_URL: &str = "https://api.openai.com/v1/images/variations";
+pub const OPENAI_TRANSCRIPTION_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
+pub const OPENAI_SPEECH_URL: &str = "https://api.openai.com/v1/audio/speech";
+pub const OPENAI_MODERATION_URL: &str = "https://api.openai.com/v1/moderations";
+
+/// The maximum number of stop sequences accepted by the API.
+pub const MAX_STOP_SEQUENCES: usize = 4;
+
+/// The maximum number of most likely tokens that can be requested per position for chat models.
+pub const MAX_TOP_LOGPROBS: u8 = 20;
+
+/// The maximum number of most likely tokens that can be requested per position for completion models.
+pub const MAX_COMPLETION_LOGPROBS: u8 = 5;
+
+/// The maximum number of images that can be generated by a single request.
+pub const MAX_IMAGES: u8 = 10;
 
 //-------------------------------------------------------------------------------------------------
 // Types
 //-------------------------------------------------------------------------------------------------
 
+/// The attributes accepted by the chat completions endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ChatAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u8>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<HashMap<u64, i8>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u8>,
+}
+
+/// The attributes accepted by the legacy completions endpoint.
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
-pub struct Attributes {
+pub struct CompletionAttributes {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suffix: Option<String>,
 
@@ -31,9 +98,6 @@ pub struct Attributes {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub n: Option<u8>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub stream: Option<bool>,
-
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<u8>,
 
@@ -41,7 +105,7 @@ pub struct Attributes {
     pub echo: Option<bool>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub stop: Option<String>,
+    pub stop: Option<Vec<String>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub presence_penalty: Option<f32>,
@@ -59,12 +123,170 @@ pub struct Attributes {
     pub user: Option<String>,
 }
 
+/// The format the chat model must output.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+}
+
+/// The attributes accepted by the image endpoints.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ImageAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u8>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<ImageQuality>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ImageResponseFormat>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<ImageSize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<ImageStyle>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// The size of the generated images.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Display)]
+pub enum ImageSize {
+    #[strum(serialize = "256x256")]
+    #[serde(rename = "256x256")]
+    Size256x256,
+
+    #[strum(serialize = "512x512")]
+    #[serde(rename = "512x512")]
+    Size512x512,
+
+    #[strum(serialize = "1024x1024")]
+    #[serde(rename = "1024x1024")]
+    Size1024x1024,
+
+    #[strum(serialize = "1792x1024")]
+    #[serde(rename = "1792x1024")]
+    Size1792x1024,
+
+    #[strum(serialize = "1024x1792")]
+    #[serde(rename = "1024x1792")]
+    Size1024x1792,
+}
+
+/// The quality of the generated images. `dall-e-2` only supports `Standard`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ImageQuality {
+    Standard,
+    Hd,
+}
+
+/// The style of the generated images. Only supported by `dall-e-3`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ImageStyle {
+    Vivid,
+    Natural,
+}
+
+/// How the generated images are returned.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResponseFormat {
+    Url,
+    B64Json,
+}
+
+/// The attributes accepted by the audio transcription endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct TranscriptionAttributes {
+    /// The language of the audio as an ISO-639-1 code, like `en`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Text that guides the style of the transcript or continues a previous segment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<TranscriptionResponseFormat>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+/// The format of the transcript.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionResponseFormat {
+    Json,
+    Text,
+    Srt,
+    VerboseJson,
+    Vtt,
+}
+
+/// The attributes accepted by the speech synthesis endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct SpeechAttributes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<SpeechFormat>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+}
+
+/// The voice used to synthesize speech.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Voice {
+    #[default]
+    Alloy,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Shimmer,
+}
+
+/// The audio format of the synthesized speech.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeechFormat {
+    Mp3,
+    Opus,
+    Aac,
+    Flac,
+    Wav,
+    Pcm,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChatConfig {
     pub model: ChatModel,
 
     #[serde(flatten)]
-    pub attributes: Attributes,
+    pub attributes: ChatAttributes,
+
+    /// When set, the user messages are checked with this moderation model before the chat model is
+    /// called. It is never sent to the chat endpoint.
+    #[serde(skip)]
+    pub moderation: Option<ModerationModel>,
+
+    /// Cancels the call, and the stream it returns, when triggered.
+    #[serde(skip)]
+    pub cancellation: Option<CancellationToken>,
+
+    /// The time the call may take overall, including reading the stream it returns.
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -72,7 +294,45 @@ pub struct CompletionConfig {
     pub model: CompletionModel,
 
     #[serde(flatten)]
-    pub attributes: Attributes,
+    pub attributes: CompletionAttributes,
+
+    /// Cancels the call, and the stream it returns, when triggered.
+    #[serde(skip)]
+    pub cancellation: Option<CancellationToken>,
+
+    /// The time the call may take overall, including reading the stream it returns.
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImageConfig {
+    pub model: ImageModel,
+
+    #[serde(flatten)]
+    pub attributes: ImageAttributes,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TranscriptionConfig {
+    pub model: TranscriptionModel,
+
+    #[serde(flatten)]
+    pub attributes: TranscriptionAttributes,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpeechConfig {
+    pub model: SpeechModel,
+    pub voice: Voice,
+
+    #[serde(flatten)]
+    pub attributes: SpeechAttributes,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModerationConfig {
+    pub model: ModerationModel,
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -81,6 +341,196 @@ pub struct CompletionConfig {
 
 pub trait OpenAIConfig: Config + Default {
     fn get_url(&self) -> &str;
+
+    /// Checks that the configured attributes are within the ranges accepted by the API.
+    fn validate(&self) -> Result<(), ValidationError>;
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl ChatAttributes {
+    /// Checks that the attributes are within the ranges accepted by the API.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_range("temperature", self.temperature, 0., 2.)?;
+        validate_range("top_p", self.top_p, 0., 1.)?;
+        validate_range("presence_penalty", self.presence_penalty, -2., 2.)?;
+        validate_range("frequency_penalty", self.frequency_penalty, -2., 2.)?;
+        validate_stop(&self.stop)?;
+        validate_logit_bias(&self.logit_bias)?;
+
+        if let Some(top_logprobs) = self.top_logprobs {
+            if top_logprobs > MAX_TOP_LOGPROBS {
+                return Err(ValidationError::TooLarge {
+                    name: "top_logprobs",
+                    value: top_logprobs as usize,
+                    max: MAX_TOP_LOGPROBS as usize,
+                });
+            }
+
+            if self.logprobs != Some(true) {
+                return Err(ValidationError::TopLogprobsWithoutLogprobs);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CompletionAttributes {
+    /// Checks that the attributes are within the ranges accepted by the API.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_range("temperature", self.temperature, 0., 2.)?;
+        validate_range("top_p", self.top_p, 0., 1.)?;
+        validate_range("presence_penalty", self.presence_penalty, -2., 2.)?;
+        validate_range("frequency_penalty", self.frequency_penalty, -2., 2.)?;
+        validate_stop(&self.stop)?;
+        validate_logit_bias(&self.logit_bias)?;
+
+        if let Some(logprobs) = self.logprobs {
+            if logprobs > MAX_COMPLETION_LOGPROBS {
+                return Err(ValidationError::TooLarge {
+                    name: "logprobs",
+                    value: logprobs as usize,
+                    max: MAX_COMPLETION_LOGPROBS as usize,
+                });
+            }
+        }
+
+        if let (Some(best_of), Some(n)) = (self.best_of, self.n) {
+            if best_of < n {
+                return Err(ValidationError::BestOfLessThanN { best_of, n });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ImageConfig {
+    /// Checks that the attributes are within the ranges accepted by the API for the configured model.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let max = match self.model {
+            ImageModel::DallE3 => 1,
+            _ => MAX_IMAGES,
+        };
+
+        if let Some(n) = self.attributes.n {
+            if n > max {
+                return Err(ValidationError::TooLarge {
+                    name: "n",
+                    value: n as usize,
+                    max: max as usize,
+                });
+            }
+        }
+
+        let (sizes, qualities, styles): (&[_], &[_], &[_]) = match self.model {
+            ImageModel::DallE2 => (
+                &[
+                    ImageSize::Size256x256,
+                    ImageSize::Size512x512,
+                    ImageSize::Size1024x1024,
+                ],
+                &[ImageQuality::Standard],
+                &[],
+            ),
+            ImageModel::DallE3 => (
+                &[
+                    ImageSize::Size1024x1024,
+                    ImageSize::Size1792x1024,
+                    ImageSize::Size1024x1792,
+                ],
+                &[ImageQuality::Standard, ImageQuality::Hd],
+                &[ImageStyle::Vivid, ImageStyle::Natural],
+            ),
+            // The options of custom models are not known.
+            ImageModel::Custom(_) => return Ok(()),
+        };
+
+        validate_supported(&self.model, "size", self.attributes.size, sizes)?;
+        validate_supported(&self.model, "quality", self.attributes.quality, qualities)?;
+        validate_supported(&self.model, "style", self.attributes.style, styles)
+    }
+}
+
+impl TranscriptionAttributes {
+    /// Checks that the attributes are within the ranges accepted by the API.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_range("temperature", self.temperature, 0., 1.)
+    }
+}
+
+impl SpeechAttributes {
+    /// Checks that the attributes are within the ranges accepted by the API.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_range("speed", self.speed, 0.25, 4.)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+fn validate_range(
+    name: &'static str,
+    value: Option<f32>,
+    min: f32,
+    max: f32,
+) -> Result<(), ValidationError> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => Err(ValidationError::OutOfRange {
+            name,
+            value,
+            min,
+            max,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn validate_supported<T>(
+    model: &ImageModel,
+    name: &'static str,
+    value: Option<T>,
+    supported: &[T],
+) -> Result<(), ValidationError>
+where
+    T: PartialEq + ToString,
+{
+    match value {
+        Some(value) if !supported.contains(&value) => Err(ValidationError::Unsupported {
+            name,
+            value: value.to_string(),
+            model: model.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn validate_stop(stop: &Option<Vec<String>>) -> Result<(), ValidationError> {
+    match stop {
+        Some(stop) if stop.len() > MAX_STOP_SEQUENCES => Err(ValidationError::TooLarge {
+            name: "stop",
+            value: stop.len(),
+            max: MAX_STOP_SEQUENCES,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn validate_logit_bias(logit_bias: &Option<HashMap<u64, i8>>) -> Result<(), ValidationError> {
+    for (token, bias) in logit_bias.iter().flatten() {
+        if !(-100..=100).contains(bias) {
+            return Err(ValidationError::LogitBiasOutOfRange {
+                token: *token,
+                bias: *bias,
+            });
+        }
+    }
+
+    Ok(())
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -91,16 +541,72 @@ impl Config for ChatConfig {}
 
 impl Config for CompletionConfig {}
 
+impl Config for ImageConfig {}
+
+impl Config for TranscriptionConfig {}
+
+impl Config for SpeechConfig {}
+
+impl Config for ModerationConfig {}
+
 impl OpenAIConfig for ChatConfig {
     fn get_url(&self) -> &str {
         OPENAI_CHAT_URL
     }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.attributes.validate()
+    }
 }
 
 impl OpenAIConfig for CompletionConfig {
     fn get_url(&self) -> &str {
         OPENAI_COMPLETION_URL
     }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.attributes.validate()
+    }
+}
+
+impl OpenAIConfig for ImageConfig {
+    fn get_url(&self) -> &str {
+        OPENAI_IMAGE_GENERATION_URL
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        ImageConfig::validate(self)
+    }
+}
+
+impl OpenAIConfig for TranscriptionConfig {
+    fn get_url(&self) -> &str {
+        OPENAI_TRANSCRIPTION_URL
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.attributes.validate()
+    }
+}
+
+impl OpenAIConfig for SpeechConfig {
+    fn get_url(&self) -> &str {
+        OPENAI_SPEECH_URL
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.attributes.validate()
+    }
+}
+
+impl OpenAIConfig for ModerationConfig {
+    fn get_url(&self) -> &str {
+        OPENAI_MODERATION_URL
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
 }
 
 impl Default for ChatConfig {
@@ -108,6 +614,9 @@ impl Default for ChatConfig {
         Self {
             model: ChatModel::GPT3_5Turbo,
             attributes: Default::default(),
+            moderation: None,
+            cancellation: None,
+            timeout: None,
         }
     }
 }
@@ -116,7 +625,167 @@ impl Default for CompletionConfig {
     fn default() -> Self {
         Self {
             model: CompletionModel::TextDaVinci003,
-            attributes: Attributes::default(),
+            attributes: CompletionAttributes::default(),
+            cancellation: None,
+            timeout: None,
+        }
+    }
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            model: ImageModel::DallE2,
+            attributes: ImageAttributes::default(),
+        }
+    }
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            model: TranscriptionModel::Whisper1,
+            attributes: TranscriptionAttributes::default(),
+        }
+    }
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            model: SpeechModel::TTS1,
+            voice: Voice::default(),
+            attributes: SpeechAttributes::default(),
         }
     }
 }
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            model: ModerationModel::TextModerationLatest,
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_attributes_serialize_only_chat_fields() {
+        let config = ChatConfig {
+            attributes: ChatAttributes {
+                stop: Some(vec!["\n".into(), "END".into()]),
+                seed: Some(42),
+                response_format: Some(ResponseFormat::JsonObject),
+                logprobs: Some(true),
+                top_logprobs: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(value["stop"], serde_json::json!(["\n", "END"]));
+        assert_eq!(value["seed"], 42);
+        assert_eq!(
+            value["response_format"],
+            serde_json::json!({ "type": "json_object" })
+        );
+        assert_eq!(value["logprobs"], true);
+        assert!(value.get("suffix").is_none());
+        assert!(value.get("echo").is_none());
+        assert!(value.get("best_of").is_none());
+    }
+
+    #[test]
+    fn test_attributes_out_of_range_are_rejected() {
+        let attributes = ChatAttributes {
+            temperature: Some(2.5),
+            ..Default::default()
+        };
+        assert!(matches!(
+            attributes.validate(),
+            Err(ValidationError::OutOfRange {
+                name: "temperature",
+                ..
+            })
+        ));
+
+        let attributes = ChatAttributes {
+            presence_penalty: Some(-2.1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            attributes.validate(),
+            Err(ValidationError::OutOfRange {
+                name: "presence_penalty",
+                ..
+            })
+        ));
+
+        let attributes = CompletionAttributes {
+            logit_bias: Some(HashMap::from([(50256, -101)])),
+            ..Default::default()
+        };
+        assert!(matches!(
+            attributes.validate(),
+            Err(ValidationError::LogitBiasOutOfRange {
+                token: 50256,
+                bias: -101
+            })
+        ));
+
+        let attributes = CompletionAttributes {
+            stop: Some(vec![
+                "a".into(),
+                "b".into(),
+                "c".into(),
+                "d".into(),
+                "e".into(),
+            ]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            attributes.validate(),
+            Err(ValidationError::TooLarge { name: "stop", .. })
+        ));
+
+        let attributes = ChatAttributes {
+            top_logprobs: Some(3),
+            ..Default::default()
+        };
+        assert!(matches!(
+            attributes.validate(),
+            Err(ValidationError::TopLogprobsWithoutLogprobs)
+        ));
+    }
+
+    #[test]
+    fn test_attributes_within_range_are_accepted() {
+        let attributes = ChatAttributes {
+            temperature: Some(2.),
+            top_p: Some(0.),
+            frequency_penalty: Some(-2.),
+            logit_bias: Some(HashMap::from([(50256, 100)])),
+            logprobs: Some(true),
+            top_logprobs: Some(20),
+            ..Default::default()
+        };
+        assert!(attributes.validate().is_ok());
+
+        let attributes = CompletionAttributes {
+            temperature: Some(0.),
+            logprobs: Some(5),
+            n: Some(2),
+            best_of: Some(3),
+            ..Default::default()
+        };
+        assert!(attributes.validate().is_ok());
+    }
+}
@@ -0,0 +1,237 @@
+use super::OpenAIError;
+use async_trait::async_trait;
+use std::{
+    env,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    path::PathBuf,
+    sync::Arc,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Constants
+//-------------------------------------------------------------------------------------------------
+
+/// The environment variable the API key is read from by default.
+pub const OPENAI_API_KEY_VAR: &str = "OPENAI_API_KEY";
+
+//-------------------------------------------------------------------------------------------------
+// Traits
+//-------------------------------------------------------------------------------------------------
+
+/// A source of OpenAI API keys.
+///
+/// Providers are evaluated lazily, every time a request is made, so they can return rotating secrets.
+#[async_trait]
+pub trait CredentialProvider: Debug + Send + Sync {
+    /// Returns the API key, or `None` if this provider has no key to offer.
+    async fn api_key(&self) -> Result<Option<String>, OpenAIError>;
+}
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// An ordered list of credential providers. The first provider that returns a key wins.
+#[derive(Debug, Clone)]
+pub struct CredentialChain {
+    providers: Vec<Arc<dyn CredentialProvider>>,
+}
+
+/// A provider that returns an explicitly given API key.
+#[derive(Clone)]
+pub struct StaticCredential(String);
+
+/// A provider that reads the API key from an environment variable.
+#[derive(Debug, Clone)]
+pub struct EnvCredential {
+    var: String,
+}
+
+/// A provider that reads the API key from a file, ignoring surrounding whitespace.
+#[derive(Debug, Clone)]
+pub struct FileCredential {
+    path: PathBuf,
+}
+
+/// A provider that calls an async function to get the API key.
+#[derive(Clone)]
+pub struct CallbackCredential<F> {
+    callback: F,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl CredentialChain {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        Self { providers: vec![] }
+    }
+
+    /// Adds a provider to the end of the chain.
+    pub fn with(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    /// Adds a provider to the front of the chain.
+    pub fn with_first(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.providers.insert(0, Arc::new(provider));
+        self
+    }
+
+    /// Returns the first API key found in the chain.
+    pub async fn resolve(&self) -> Result<String, OpenAIError> {
+        for provider in self.providers.iter() {
+            if let Some(api_key) = provider.api_key().await? {
+                return Ok(api_key);
+            }
+        }
+
+        Err(OpenAIError::MissingAPIKey)
+    }
+}
+
+impl StaticCredential {
+    /// Creates a provider that returns the given API key.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self(api_key.into())
+    }
+}
+
+impl EnvCredential {
+    /// Creates a provider that reads the given environment variable.
+    pub fn new(var: impl Into<String>) -> Self {
+        Self { var: var.into() }
+    }
+}
+
+impl FileCredential {
+    /// Creates a provider that reads the file at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl<F> CallbackCredential<F> {
+    /// Creates a provider that calls the given function.
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+#[async_trait]
+impl CredentialProvider for StaticCredential {
+    async fn api_key(&self) -> Result<Option<String>, OpenAIError> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for EnvCredential {
+    async fn api_key(&self) -> Result<Option<String>, OpenAIError> {
+        Ok(env::var(&self.var).ok())
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for FileCredential {
+    async fn api_key(&self) -> Result<Option<String>, OpenAIError> {
+        match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => Ok(Some(contents.trim().to_string())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(OpenAIError::Io(err)),
+        }
+    }
+}
+
+#[async_trait]
+impl<F, Fut> CredentialProvider for CallbackCredential<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Option<String>, OpenAIError>> + Send,
+{
+    async fn api_key(&self) -> Result<Option<String>, OpenAIError> {
+        (self.callback)().await
+    }
+}
+
+impl Default for CredentialChain {
+    fn default() -> Self {
+        Self::new().with(EnvCredential::new(OPENAI_API_KEY_VAR))
+    }
+}
+
+impl Debug for StaticCredential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StaticCredential").field(&"***").finish()
+    }
+}
+
+impl<F> Debug for CallbackCredential<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackCredential").finish_non_exhaustive()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chain_returns_first_available_key() {
+        let chain = CredentialChain::new()
+            .with(EnvCredential::new("VERSA_TEST_UNSET_API_KEY"))
+            .with(FileCredential::new("/nonexistent/versa/api-key"))
+            .with(CallbackCredential::new(|| async {
+                Ok(Some("sk-callback".to_string()))
+            }))
+            .with(StaticCredential::new("sk-static"));
+
+        assert_eq!(chain.resolve().await.unwrap(), "sk-callback");
+
+        let chain = chain.with_first(StaticCredential::new("sk-first"));
+        assert_eq!(chain.resolve().await.unwrap(), "sk-first");
+    }
+
+    #[tokio::test]
+    async fn test_chain_can_be_resolved_on_another_task() {
+        let chain = CredentialChain::new().with(CallbackCredential::new(|| async {
+            Ok(Some("sk-task".to_string()))
+        }));
+
+        let key = tokio::spawn(async move { chain.resolve().await }).await;
+        assert_eq!(key.unwrap().unwrap(), "sk-task");
+    }
+
+    #[tokio::test]
+    async fn test_empty_chain_reports_missing_key() {
+        let chain = CredentialChain::new().with(EnvCredential::new("VERSA_TEST_UNSET_API_KEY"));
+
+        assert!(matches!(
+            chain.resolve().await,
+            Err(OpenAIError::MissingAPIKey)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_credential_trims_contents() {
+        let path = env::temp_dir().join(format!("versa-api-key-{}", std::process::id()));
+        tokio::fs::write(&path, "sk-from-file\n").await.unwrap();
+
+        let key = FileCredential::new(&path).api_key().await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(key.as_deref(), Some("sk-from-file"));
+    }
+}
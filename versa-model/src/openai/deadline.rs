@@ -0,0 +1,129 @@
+//! Cancellation and overall deadlines for model calls.
+
+use super::OpenAIError;
+use std::{
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Sleep;
+
+pub use tokio_util::sync::CancellationToken;
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// Watches the cancellation token and deadline of a call, including any stream it returns.
+#[derive(Default)]
+pub(crate) struct Deadline {
+    cancelled: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl Deadline {
+    /// Starts the deadline, which expires `timeout` from now.
+    pub(crate) fn new(cancellation: Option<&CancellationToken>, timeout: Option<Duration>) -> Self {
+        Self {
+            cancelled: cancellation.cloned().map(|token| {
+                Box::pin(async move { token.cancelled().await })
+                    as Pin<Box<dyn Future<Output = ()> + Send>>
+            }),
+            sleep: timeout.map(|timeout| Box::pin(tokio::time::sleep(timeout))),
+        }
+    }
+
+    /// Resolves with the error to report once the call is cancelled or its deadline has passed.
+    ///
+    /// The deadline is disarmed after it fires.
+    pub(crate) fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<OpenAIError> {
+        let cancelled = match self.cancelled.as_mut() {
+            Some(cancelled) => cancelled.as_mut().poll(cx).is_ready(),
+            None => false,
+        };
+
+        let error = if cancelled {
+            OpenAIError::Cancelled {
+                partial: String::new(),
+            }
+        } else if let Some(Poll::Ready(())) =
+            self.sleep.as_mut().map(|sleep| sleep.as_mut().poll(cx))
+        {
+            OpenAIError::Timeout {
+                partial: String::new(),
+            }
+        } else {
+            return Poll::Pending;
+        };
+
+        *self = Self::default();
+        Poll::Ready(error)
+    }
+
+    /// Runs `future` until it completes, unless the call is cancelled or its deadline passes first.
+    pub(crate) async fn run<T, E>(
+        &mut self,
+        future: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E>
+    where
+        E: From<OpenAIError>,
+    {
+        let mut future = Box::pin(future);
+        futures::future::poll_fn(|cx| {
+            if let Poll::Ready(error) = self.poll_expired(cx) {
+                return Poll::Ready(Err(error.into()));
+            }
+
+            future.as_mut().poll(cx)
+        })
+        .await
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl Debug for Deadline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deadline")
+            .field("cancellable", &self.cancelled.is_some())
+            .field(
+                "deadline",
+                &self.sleep.as_ref().map(|sleep| sleep.deadline()),
+            )
+            .finish()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_stops_on_cancellation_and_timeout() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut deadline = Deadline::new(Some(&token), None);
+        let result: Result<(), OpenAIError> = deadline.run(futures::future::pending()).await;
+        assert!(matches!(result, Err(OpenAIError::Cancelled { .. })));
+
+        let mut deadline = Deadline::new(None, Some(Duration::from_millis(10)));
+        let result: Result<(), OpenAIError> = deadline.run(futures::future::pending()).await;
+        assert!(matches!(result, Err(OpenAIError::Timeout { .. })));
+
+        let mut deadline = Deadline::new(None, Some(Duration::from_secs(10)));
+        let result: Result<u8, OpenAIError> = deadline.run(async { Ok(1) }).await;
+        assert_eq!(result.unwrap(), 1);
+    }
+}
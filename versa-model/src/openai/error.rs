@@ -1,4 +1,5 @@
-use std::fmt::Display;
+use super::ImageResponseFormat;
+use std::{fmt::Display, sync::Arc, time::Duration};
 
 use serde::Deserialize;
 use thiserror::Error;
@@ -16,7 +17,7 @@ pub enum OpenAIError {
     Reqwest(#[from] reqwest::Error),
 
     #[error("eventsource: {0}")]
-    EventSource(#[from] reqwest_eventsource::Error),
+    EventSource(Box<reqwest_eventsource::Error>),
 
     #[error("serde_json: {0}")]
     SerdeJson(#[from] serde_json::Error),
@@ -27,8 +28,78 @@ pub enum OpenAIError {
     #[error("completion missing from response")]
     CompletionMissing,
 
+    #[error("image missing from response in the requested `{0}` format")]
+    ImageMissing(ImageResponseFormat),
+
     #[error("missing api key")]
     MissingAPIKey,
+
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("validation: {0}")]
+    Validation(#[from] ValidationError),
+
+    #[error("stream idle for more than {0:?}")]
+    IdleTimeout(Duration),
+
+    #[error("{0}")]
+    Shared(Arc<OpenAIError>),
+
+    #[cfg(feature = "sled")]
+    #[error("sled: {0}")]
+    Sled(#[from] sled::Error),
+
+    #[error("cancelled")]
+    Cancelled { partial: String },
+
+    #[error("deadline exceeded")]
+    Timeout { partial: String },
+
+    #[error("invalid arguments for tool call `{name}`: {source}")]
+    InvalidToolArguments {
+        name: String,
+        arguments: String,
+        source: serde_json::Error,
+    },
+}
+
+/// An error returned when a configured attribute is rejected before making a request.
+#[derive(Debug, Error, PartialEq)]
+pub enum ValidationError {
+    #[error("`{name}` must be between {min} and {max}, got {value}")]
+    OutOfRange {
+        name: &'static str,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+
+    #[error("`{name}` must be at most {max}, got {value}")]
+    TooLarge {
+        name: &'static str,
+        value: usize,
+        max: usize,
+    },
+
+    #[error("logit bias for token {token} must be between -100 and 100, got {bias}")]
+    LogitBiasOutOfRange { token: u64, bias: i8 },
+
+    #[error("`top_logprobs` requires `logprobs` to be enabled")]
+    TopLogprobsWithoutLogprobs,
+
+    #[error("`best_of` ({best_of}) must be greater than or equal to `n` ({n})")]
+    BestOfLessThanN { best_of: u8, n: u8 },
+
+    #[error("`{name}` {value} is not supported by {model}")]
+    Unsupported {
+        name: &'static str,
+        value: String,
+        model: String,
+    },
 }
 
 #[derive(Debug, Deserialize, Error)]
@@ -49,6 +120,12 @@ pub struct InnerError {
 // Trait Implementations
 //-------------------------------------------------------------------------------------------------
 
+impl From<reqwest_eventsource::Error> for OpenAIError {
+    fn from(err: reqwest_eventsource::Error) -> Self {
+        Self::EventSource(Box::new(err))
+    }
+}
+
 impl Display for InnerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("InnerError")
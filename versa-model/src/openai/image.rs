@@ -0,0 +1,332 @@
+//! This module contains the implementation of the OpenAI image model.
+
+use super::{
+    form_value, telemetry::CallSpan, ImageConfig, ImageModel, ImageQuality, ImageRequest,
+    ImageResponseFormat, ImageSize, ImageStyle, OpenAIError, OpenAIImageModel, UploadFile,
+    OPENAI_IMAGE_EDIT_URL, OPENAI_IMAGE_GENERATION_URL, OPENAI_IMAGE_VARIATION_URL,
+};
+use crate::{ModelError, Output};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{multipart::Form, Client};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct ImageBody {
+    pub prompt: String,
+
+    #[serde(flatten)]
+    pub config: ImageConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImageModelResponse {
+    pub created: u64,
+    pub data: Vec<ImageData>,
+}
+
+/// A generated image, returned either as a URL or as base64-encoded JSON depending on the
+/// requested response format.
+#[derive(Debug, Deserialize)]
+pub struct ImageData {
+    pub url: Option<String>,
+    pub b64_json: Option<String>,
+    pub revised_prompt: Option<String>,
+}
+
+/// A generated image decoded into bytes.
+#[derive(Debug, Clone)]
+pub struct GeneratedImage {
+    pub bytes: Vec<u8>,
+    pub revised_prompt: Option<String>,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+// TODO(nyprothegeek): Document the builder methods properly.
+impl OpenAIImageModel {
+    /// Sets the model.
+    pub fn model(mut self, model: ImageModel) -> Self {
+        self.config.model = model;
+        self
+    }
+
+    /// Sets the number of images to generate. `dall-e-3` only supports 1.
+    pub fn n(mut self, n: u8) -> Self {
+        self.config.attributes.n = Some(n);
+        self
+    }
+
+    /// Sets the quality.
+    pub fn quality(mut self, quality: ImageQuality) -> Self {
+        self.config.attributes.quality = Some(quality);
+        self
+    }
+
+    /// Sets the response format.
+    pub fn response_format(mut self, response_format: ImageResponseFormat) -> Self {
+        self.config.attributes.response_format = Some(response_format);
+        self
+    }
+
+    /// Sets the size.
+    pub fn size(mut self, size: ImageSize) -> Self {
+        self.config.attributes.size = Some(size);
+        self
+    }
+
+    /// Sets the style.
+    pub fn style(mut self, style: ImageStyle) -> Self {
+        self.config.attributes.style = Some(style);
+        self
+    }
+
+    /// Sets the user token.
+    pub fn user(mut self, user_token: impl Into<String>) -> Self {
+        self.config.attributes.user = Some(user_token.into());
+        self
+    }
+}
+
+impl GeneratedImage {
+    /// Writes the image to the given path.
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<(), OpenAIError> {
+        Ok(tokio::fs::write(path, &self.bytes).await?)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Creates the multipart form shared by the edit and variation endpoints.
+///
+/// Quality and style are left out because these endpoints do not accept them.
+fn image_form(config: &ImageConfig, image: UploadFile) -> Result<Form, OpenAIError> {
+    let attributes = &config.attributes;
+    let mut form = Form::new()
+        .text("model", config.model.to_string())
+        .part("image", image.into_part());
+
+    if let Some(n) = attributes.n {
+        form = form.text("n", n.to_string());
+    }
+
+    if let Some(size) = attributes.size {
+        form = form.text("size", form_value(&size)?);
+    }
+
+    if let Some(response_format) = attributes.response_format {
+        form = form.text("response_format", form_value(&response_format)?);
+    }
+
+    if let Some(user) = &attributes.user {
+        form = form.text("user", user.clone());
+    }
+
+    Ok(form)
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+#[async_trait(?Send)]
+impl Output<OpenAIImageModel> for ImageModelResponse {
+    async fn from_call_with_config(
+        input: impl Into<ImageRequest>,
+        model: &OpenAIImageModel,
+        config: ImageConfig,
+    ) -> Result<Self, ModelError> {
+        config.validate().map_err(OpenAIError::Validation)?;
+
+        let request = input.into();
+        let url = match &request {
+            ImageRequest::Generate { .. } => OPENAI_IMAGE_GENERATION_URL,
+            ImageRequest::Edit { .. } => OPENAI_IMAGE_EDIT_URL,
+            ImageRequest::Variation { .. } => OPENAI_IMAGE_VARIATION_URL,
+        };
+
+        let span = CallSpan::new(&config.model, url, false);
+        let call = async {
+            match request {
+                ImageRequest::Generate { prompt } => {
+                    let body = ImageBody { prompt, config };
+                    span.record_request(&body);
+                    model.post(url, &body).await
+                }
+                ImageRequest::Edit {
+                    image,
+                    mask,
+                    prompt,
+                } => {
+                    let mut form = image_form(&config, image)?.text("prompt", prompt);
+                    if let Some(mask) = mask {
+                        form = form.part("mask", mask.into_part());
+                    }
+
+                    model.send(Client::new().post(url).multipart(form)).await
+                }
+                ImageRequest::Variation { image } => {
+                    let form = image_form(&config, image)?;
+                    model.send(Client::new().post(url).multipart(form)).await
+                }
+            }
+        };
+
+        let result = span.instrument(call).await;
+        span.record_result(&result, |_| None, |_| None);
+
+        Ok(result?)
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAIImageModel> for Vec<String> {
+    async fn from_call_with_config(
+        input: impl Into<ImageRequest>,
+        model: &OpenAIImageModel,
+        mut config: ImageConfig,
+    ) -> Result<Self, ModelError> {
+        config.attributes.response_format = Some(ImageResponseFormat::Url);
+        let response = ImageModelResponse::from_call_with_config(input, model, config).await?;
+
+        Ok(response
+            .data
+            .into_iter()
+            .map(|image| {
+                image
+                    .url
+                    .ok_or(OpenAIError::ImageMissing(ImageResponseFormat::Url))
+            })
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAIImageModel> for Vec<GeneratedImage> {
+    async fn from_call_with_config(
+        input: impl Into<ImageRequest>,
+        model: &OpenAIImageModel,
+        mut config: ImageConfig,
+    ) -> Result<Self, ModelError> {
+        config.attributes.response_format = Some(ImageResponseFormat::B64Json);
+        let response = ImageModelResponse::from_call_with_config(input, model, config).await?;
+
+        Ok(response
+            .data
+            .into_iter()
+            .map(|image| {
+                let data = image
+                    .b64_json
+                    .ok_or(OpenAIError::ImageMissing(ImageResponseFormat::B64Json))?;
+                Ok(GeneratedImage {
+                    bytes: STANDARD.decode(data).map_err(OpenAIError::Base64)?,
+                    revised_prompt: image.revised_prompt,
+                })
+            })
+            .collect::<Result<_, OpenAIError>>()?)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai::ValidationError;
+
+    #[test]
+    fn test_image_body_serializes_typed_options() {
+        let body = ImageBody {
+            prompt: "A watercolor fox".into(),
+            config: ImageConfig {
+                model: ImageModel::DallE3,
+                attributes: crate::openai::ImageAttributes {
+                    quality: Some(ImageQuality::Hd),
+                    size: Some(ImageSize::Size1792x1024),
+                    style: Some(ImageStyle::Natural),
+                    response_format: Some(ImageResponseFormat::B64Json),
+                    ..Default::default()
+                },
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            serde_json::json!({
+                "prompt": "A watercolor fox",
+                "model": "dall-e-3",
+                "quality": "hd",
+                "size": "1792x1024",
+                "style": "natural",
+                "response_format": "b64_json"
+            })
+        );
+    }
+
+    #[test]
+    fn test_dall_e_3_only_generates_one_image() {
+        let mut config = ImageConfig {
+            model: ImageModel::DallE3,
+            ..Default::default()
+        };
+        config.attributes.n = Some(2);
+
+        assert_eq!(
+            config.validate(),
+            Err(ValidationError::TooLarge {
+                name: "n",
+                value: 2,
+                max: 1
+            })
+        );
+
+        config.model = ImageModel::DallE2;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_image_options_are_checked_per_model() {
+        let mut config = ImageConfig::default();
+        config.attributes.size = Some(ImageSize::Size1792x1024);
+        assert_eq!(
+            config.validate(),
+            Err(ValidationError::Unsupported {
+                name: "size",
+                value: "1792x1024".into(),
+                model: "dall-e-2".into()
+            })
+        );
+
+        config.attributes.size = Some(ImageSize::Size512x512);
+        config.attributes.style = Some(ImageStyle::Vivid);
+        assert!(matches!(
+            config.validate(),
+            Err(ValidationError::Unsupported { name: "style", .. })
+        ));
+
+        config.model = ImageModel::DallE3;
+        assert!(matches!(
+            config.validate(),
+            Err(ValidationError::Unsupported { name: "size", .. })
+        ));
+
+        config.attributes.size = Some(ImageSize::Size1024x1792);
+        config.attributes.quality = Some(ImageQuality::Hd);
+        assert!(config.validate().is_ok());
+
+        config.model = ImageModel::Custom("my-image-model".into());
+        config.attributes.size = Some(ImageSize::Size256x256);
+        assert!(config.validate().is_ok());
+    }
+}
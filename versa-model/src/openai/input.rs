@@ -1,6 +1,11 @@
+use super::OpenAIError;
+use reqwest::multipart::Part;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use strum_macros::Display;
-use versa_prompt::{ResolvedPrompt, ResolvedPromptList, Role, Tag};
+use versa_prompt::{
+    Content, ContentPart, Image, ImageDetail, ResolvedPrompt, ResolvedPromptList, Role, Tag,
+};
 
 //-------------------------------------------------------------------------------------------------
 // Types
@@ -20,22 +25,243 @@ pub enum ChatRole {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: ChatRole,
-    pub content: String,
+    pub content: ChatContent,
+}
+
+/// The content of a chat message, either plain text or an ordered list of parts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ChatContent {
+    Text(String),
+    Parts(Vec<ChatContentPart>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageUrl {
+    /// Either a URL of the image or the base64-encoded image data as a data URL.
+    pub url: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ChatStreamMessage {
     pub role: Option<ChatRole>,
     pub content: Option<String>,
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// A fragment of a tool call, as streamed by the model.
+///
+/// The `id` and function `name` only come with the first fragment of each call, while `arguments`
+/// is spread across all of them.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub r#type: Option<String>,
+    pub function: Option<FunctionCallDelta>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct FunctionCallDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
 }
 
 #[derive(Debug, Serialize, Default)]
 pub struct ChatMessages(Vec<ChatMessage>);
 
+/// The text checked by the moderation endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ModerationInput(Vec<String>);
+
+/// A request to one of the image endpoints.
+#[derive(Debug, Clone)]
+pub enum ImageRequest {
+    /// Creates images from a prompt.
+    Generate { prompt: String },
+
+    /// Edits an image given a prompt. Transparent areas of the mask, or of the image itself if no
+    /// mask is given, indicate where the image should be edited.
+    Edit {
+        image: UploadFile,
+        mask: Option<UploadFile>,
+        prompt: String,
+    },
+
+    /// Creates variations of an image.
+    Variation { image: UploadFile },
+}
+
+/// A file uploaded as part of a multipart request.
+#[derive(Debug, Clone)]
+pub struct UploadFile {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl ImageRequest {
+    /// Creates a request for images generated from the prompt.
+    pub fn generate(prompt: impl Into<String>) -> Self {
+        ImageRequest::Generate {
+            prompt: prompt.into(),
+        }
+    }
+
+    /// Creates a request for an edit of the image.
+    pub fn edit(image: UploadFile, prompt: impl Into<String>) -> Self {
+        ImageRequest::Edit {
+            image,
+            mask: None,
+            prompt: prompt.into(),
+        }
+    }
+
+    /// Creates a request for variations of the image.
+    pub fn variation(image: UploadFile) -> Self {
+        ImageRequest::Variation { image }
+    }
+
+    /// Sets the mask of an edit request. Does nothing for other requests.
+    pub fn mask(mut self, mask: UploadFile) -> Self {
+        if let ImageRequest::Edit { mask: m, .. } = &mut self {
+            *m = Some(mask);
+        }
+        self
+    }
+}
+
+impl UploadFile {
+    /// Creates a file from its name and contents.
+    pub fn new(name: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.into(),
+            bytes: bytes.into(),
+        }
+    }
+
+    /// Reads the file at the given path.
+    pub async fn from_path(path: impl AsRef<Path>) -> Result<Self, OpenAIError> {
+        let path = path.as_ref();
+        let bytes = tokio::fs::read(path).await?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Self { name, bytes })
+    }
+
+    /// Converts the file into a part of a multipart form.
+    pub(crate) fn into_part(self) -> Part {
+        Part::bytes(self.bytes).file_name(self.name)
+    }
+}
+
+impl ChatMessages {
+    /// Returns an iterator over the messages.
+    pub fn iter(&self) -> impl Iterator<Item = &ChatMessage> {
+        self.0.iter()
+    }
+}
+
+impl ChatContent {
+    /// Returns the text of the content, joining the text parts and skipping images.
+    pub fn text(&self) -> String {
+        match self {
+            ChatContent::Text(text) => text.clone(),
+            ChatContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ChatContentPart::Text { text } => Some(text.as_str()),
+                    ChatContentPart::ImageUrl { .. } => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Converts a serializable value into the text of a multipart form field.
+pub(crate) fn form_value(value: &impl Serialize) -> Result<String, OpenAIError> {
+    Ok(match serde_json::to_value(value)? {
+        serde_json::Value::String(value) => value,
+        value => value.to_string(),
+    })
+}
+
 //-------------------------------------------------------------------------------------------------
 // Trait Implementations
 //-------------------------------------------------------------------------------------------------
 
+impl From<String> for ChatContent {
+    fn from(text: String) -> Self {
+        ChatContent::Text(text)
+    }
+}
+
+impl From<&str> for ChatContent {
+    fn from(text: &str) -> Self {
+        ChatContent::Text(text.to_string())
+    }
+}
+
+impl From<ChatContent> for String {
+    fn from(content: ChatContent) -> Self {
+        match content {
+            ChatContent::Text(text) => text,
+            content => content.text(),
+        }
+    }
+}
+
+impl From<Content> for ChatContent {
+    fn from(content: Content) -> Self {
+        match content {
+            Content::Text(text) => ChatContent::Text(text),
+            Content::Parts(parts) => {
+                ChatContent::Parts(parts.into_iter().map(ChatContentPart::from).collect())
+            }
+        }
+    }
+}
+
+impl From<ContentPart> for ChatContentPart {
+    fn from(part: ContentPart) -> Self {
+        match part {
+            ContentPart::Text(text) => ChatContentPart::Text { text },
+            ContentPart::Image(image) => ChatContentPart::ImageUrl {
+                image_url: image.into(),
+            },
+        }
+    }
+}
+
+impl From<Image> for ImageUrl {
+    fn from(image: Image) -> Self {
+        Self {
+            url: image.to_url(),
+            detail: image.detail,
+        }
+    }
+}
+
 impl From<Vec<ChatMessage>> for ChatMessages {
     fn from(v: Vec<ChatMessage>) -> Self {
         Self(v)
@@ -46,7 +272,7 @@ impl From<String> for ChatMessages {
     fn from(s: String) -> Self {
         Self(vec![ChatMessage {
             role: ChatRole::User,
-            content: s,
+            content: s.into(),
         }])
     }
 }
@@ -55,7 +281,7 @@ impl From<&str> for ChatMessages {
     fn from(s: &str) -> Self {
         Self(vec![ChatMessage {
             role: ChatRole::User,
-            content: s.to_string(),
+            content: s.into(),
         }])
     }
 }
@@ -82,17 +308,109 @@ impl From<ResolvedPromptList> for ChatMessages {
                 })
                 .unwrap_or(ChatRole::User);
 
-            messages.push(ChatMessage { role, content });
+            messages.push(ChatMessage {
+                role,
+                content: content.into(),
+            });
         }
         Self(messages)
     }
 }
 
+impl From<String> for ImageRequest {
+    fn from(prompt: String) -> Self {
+        ImageRequest::generate(prompt)
+    }
+}
+
+impl From<&str> for ImageRequest {
+    fn from(prompt: &str) -> Self {
+        ImageRequest::generate(prompt)
+    }
+}
+
+impl From<ResolvedPrompt> for ImageRequest {
+    fn from(prompt: ResolvedPrompt) -> Self {
+        ImageRequest::generate(String::from(prompt))
+    }
+}
+
+impl From<String> for ModerationInput {
+    fn from(text: String) -> Self {
+        Self(vec![text])
+    }
+}
+
+impl From<&str> for ModerationInput {
+    fn from(text: &str) -> Self {
+        Self(vec![text.to_string()])
+    }
+}
+
+impl From<Vec<String>> for ModerationInput {
+    fn from(texts: Vec<String>) -> Self {
+        Self(texts)
+    }
+}
+
+impl From<ResolvedPrompt> for ModerationInput {
+    fn from(prompt: ResolvedPrompt) -> Self {
+        Self(vec![prompt.into()])
+    }
+}
+
+impl From<ModerationInput> for Vec<String> {
+    fn from(input: ModerationInput) -> Self {
+        input.0
+    }
+}
+
 impl From<ResolvedPrompt> for ChatMessages {
     fn from(prompt: ResolvedPrompt) -> Self {
         Self(vec![ChatMessage {
             role: ChatRole::User,
-            content: prompt.into(),
+            content: String::from(prompt).into(),
         }])
     }
 }
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use versa_prompt::{prompt, FinalizablePrompt};
+
+    #[test]
+    fn test_prompt_list_with_images_converts_to_content_parts() {
+        let prompt = prompt!(
+            system: "You describe images.",
+            user: {
+                "What is in this image?",
+                Image::url("https://example.com/cat.png").detail(ImageDetail::High)
+            }
+        );
+
+        let messages: Vec<ChatMessage> = ChatMessages::from(prompt.finalize().unwrap()).into();
+        let value = serde_json::to_value(&messages).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!([
+                { "role": "system", "content": "You describe images." },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "What is in this image?" },
+                        {
+                            "type": "image_url",
+                            "image_url": { "url": "https://example.com/cat.png", "detail": "high" }
+                        }
+                    ]
+                }
+            ])
+        );
+    }
+}
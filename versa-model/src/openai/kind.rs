@@ -1,6 +1,10 @@
-use super::{ChatConfig, ChatMessages, CompletionConfig, OpenAIConfig};
+use super::{
+    ChatConfig, ChatMessages, CompletionConfig, ImageConfig, ImageRequest, ModerationConfig,
+    ModerationInput, OpenAIConfig, SpeechConfig, TranscriptionConfig, UploadFile,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use strum_macros::Display;
+use std::str::FromStr;
+use strum_macros::{Display, EnumString};
 
 //-------------------------------------------------------------------------------------------------
 // Traits
@@ -9,13 +13,20 @@ use strum_macros::Display;
 pub trait ModelKind: Clone + Serialize + DeserializeOwned {
     type Config: OpenAIConfig;
     type Input;
+
+    /// Maps a model id onto a known variant, falling back to a custom model.
+    fn from_id(id: &str) -> Self;
 }
 
 //-------------------------------------------------------------------------------------------------
 // Types
 //-------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display)]
+/// Models served by the chat completions endpoint.
+///
+/// Names that are not listed here, like fine-tunes (`ft:gpt-3.5-turbo:org:...`), newer snapshots or
+/// models served by compatible servers, are represented by the `Custom` variant.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
 pub enum ChatModel {
     #[strum(serialize = "gpt-3.5-turbo-0613")]
     #[serde(rename = "gpt-3.5-turbo-0613")]
@@ -36,9 +47,16 @@ pub enum ChatModel {
     #[strum(serialize = "gpt-3.5-turbo-16k")]
     #[serde(rename = "gpt-3.5-turbo-16k")]
     GPT3_5Turbo16k,
+
+    #[strum(default)]
+    #[serde(untagged)]
+    Custom(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display)]
+/// Models served by the legacy completions endpoint.
+///
+/// Names that are not listed here are represented by the `Custom` variant.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
 pub enum CompletionModel {
     #[strum(serialize = "babbage")]
     #[serde(rename = "babbage")]
@@ -219,6 +237,70 @@ pub enum CompletionModel {
     #[strum(serialize = "davinci-similarity")]
     #[serde(rename = "davinci-similarity")]
     DaVinciSimilarity,
+
+    #[strum(default)]
+    #[serde(untagged)]
+    Custom(String),
+}
+
+/// Models served by the image endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
+pub enum ImageModel {
+    #[strum(serialize = "dall-e-2")]
+    #[serde(rename = "dall-e-2")]
+    DallE2,
+
+    #[strum(serialize = "dall-e-3")]
+    #[serde(rename = "dall-e-3")]
+    DallE3,
+
+    #[strum(default)]
+    #[serde(untagged)]
+    Custom(String),
+}
+
+/// Models served by the audio transcription endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
+pub enum TranscriptionModel {
+    #[strum(serialize = "whisper-1")]
+    #[serde(rename = "whisper-1")]
+    Whisper1,
+
+    #[strum(default)]
+    #[serde(untagged)]
+    Custom(String),
+}
+
+/// Models served by the speech synthesis endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
+pub enum SpeechModel {
+    #[strum(serialize = "tts-1")]
+    #[serde(rename = "tts-1")]
+    TTS1,
+
+    #[strum(serialize = "tts-1-hd")]
+    #[serde(rename = "tts-1-hd")]
+    TTS1HD,
+
+    #[strum(default)]
+    #[serde(untagged)]
+    Custom(String),
+}
+
+/// Models served by the moderation endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
+pub enum ModerationModel {
+    #[strum(serialize = "text-moderation-latest")]
+    #[serde(rename = "text-moderation-latest")]
+    TextModerationLatest,
+
+    #[strum(serialize = "text-moderation-stable")]
+    #[serde(rename = "text-moderation-stable")]
+    TextModerationStable,
+
+    #[strum(default)]
+    #[serde(untagged)]
+    Custom(String),
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -228,9 +310,102 @@ pub enum CompletionModel {
 impl ModelKind for ChatModel {
     type Config = ChatConfig;
     type Input = ChatMessages;
+
+    fn from_id(id: &str) -> Self {
+        Self::from_str(id).unwrap_or_else(|_| Self::Custom(id.to_string()))
+    }
 }
 
 impl ModelKind for CompletionModel {
     type Config = CompletionConfig;
     type Input = String;
+
+    fn from_id(id: &str) -> Self {
+        Self::from_str(id).unwrap_or_else(|_| Self::Custom(id.to_string()))
+    }
+}
+
+impl ModelKind for ImageModel {
+    type Config = ImageConfig;
+    type Input = ImageRequest;
+
+    fn from_id(id: &str) -> Self {
+        Self::from_str(id).unwrap_or_else(|_| Self::Custom(id.to_string()))
+    }
+}
+
+impl ModelKind for TranscriptionModel {
+    type Config = TranscriptionConfig;
+    type Input = UploadFile;
+
+    fn from_id(id: &str) -> Self {
+        Self::from_str(id).unwrap_or_else(|_| Self::Custom(id.to_string()))
+    }
+}
+
+impl ModelKind for SpeechModel {
+    type Config = SpeechConfig;
+    type Input = String;
+
+    fn from_id(id: &str) -> Self {
+        Self::from_str(id).unwrap_or_else(|_| Self::Custom(id.to_string()))
+    }
+}
+
+impl ModelKind for ModerationModel {
+    type Config = ModerationConfig;
+    type Input = ModerationInput;
+
+    fn from_id(id: &str) -> Self {
+        Self::from_str(id).unwrap_or_else(|_| Self::Custom(id.to_string()))
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_models_round_trip() {
+        let model = ChatModel::Custom("ft:gpt-3.5-turbo:org:custom:id".into());
+        let json = serde_json::to_string(&model).unwrap();
+
+        assert_eq!(json, r#""ft:gpt-3.5-turbo:org:custom:id""#);
+        assert_eq!(model.to_string(), "ft:gpt-3.5-turbo:org:custom:id");
+        assert_eq!(serde_json::from_str::<ChatModel>(&json).unwrap(), model);
+
+        let model = CompletionModel::Custom("my-local-model".into());
+        let json = serde_json::to_string(&model).unwrap();
+
+        assert_eq!(json, r#""my-local-model""#);
+        assert_eq!(model.to_string(), "my-local-model");
+        assert_eq!(
+            serde_json::from_str::<CompletionModel>(&json).unwrap(),
+            model
+        );
+    }
+
+    #[test]
+    fn test_known_ids_map_to_variants() {
+        assert_eq!(
+            serde_json::from_str::<ChatModel>(r#""gpt-3.5-turbo""#).unwrap(),
+            ChatModel::GPT3_5Turbo
+        );
+        assert_eq!(
+            ChatModel::from_id("gpt-3.5-turbo-16k"),
+            ChatModel::GPT3_5Turbo16k
+        );
+        assert_eq!(
+            ChatModel::from_id("gpt-4"),
+            ChatModel::Custom("gpt-4".into())
+        );
+        assert_eq!(
+            CompletionModel::from_id("text-davinci-003"),
+            CompletionModel::TextDaVinci003
+        );
+    }
 }
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// Log probability information returned by the completions endpoint.
+///
+/// All the vectors are aligned: the `n`th entry of each describes the `n`th generated token.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CompletionLogprobs {
+    /// The generated tokens.
+    pub tokens: Vec<String>,
+
+    /// The log probability of each token. The first token of an echoed prompt has none.
+    pub token_logprobs: Vec<Option<f32>>,
+
+    /// The most likely alternatives at each position, keyed by token.
+    #[serde(default)]
+    pub top_logprobs: Option<Vec<Option<HashMap<String, f32>>>>,
+
+    /// The character offset of each token in the output text.
+    pub text_offset: Vec<usize>,
+}
+
+/// Log probability information returned by the chat completions endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ChatLogprobs {
+    /// The log probabilities of the content tokens.
+    pub content: Option<Vec<ChatTokenLogprob>>,
+}
+
+/// The log probability of a single chat token, along with its most likely alternatives.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChatTokenLogprob {
+    pub token: String,
+    pub logprob: f32,
+    pub bytes: Option<Vec<u8>>,
+
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// An alternative token considered at a position.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f32,
+    pub bytes: Option<Vec<u8>>,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl CompletionLogprobs {
+    /// Returns the tokens along with their log probabilities.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<f32>)> {
+        self.tokens
+            .iter()
+            .map(String::as_str)
+            .zip(self.token_logprobs.iter().copied())
+    }
+
+    /// Returns the sum of the token log probabilities, which is the log probability of the whole text.
+    pub fn total_logprob(&self) -> f32 {
+        self.token_logprobs.iter().flatten().sum()
+    }
+
+    /// Returns the joint probability of the generated tokens.
+    pub fn probability(&self) -> f32 {
+        self.total_logprob().exp()
+    }
+}
+
+impl ChatLogprobs {
+    /// Returns the content tokens along with their log probabilities.
+    pub fn iter(&self) -> impl Iterator<Item = &ChatTokenLogprob> {
+        self.content.iter().flatten()
+    }
+
+    /// Returns the sum of the token log probabilities, which is the log probability of the whole content.
+    pub fn total_logprob(&self) -> f32 {
+        self.iter().map(|token| token.logprob).sum()
+    }
+
+    /// Returns the joint probability of the content tokens.
+    pub fn probability(&self) -> f32 {
+        self.total_logprob().exp()
+    }
+}
+
+impl ChatTokenLogprob {
+    /// Returns the probability of the token.
+    pub fn probability(&self) -> f32 {
+        self.logprob.exp()
+    }
+}
+
+impl TopLogprob {
+    /// Returns the probability of the token.
+    pub fn probability(&self) -> f32 {
+        self.logprob.exp()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_deserialize_completion_logprobs() {
+        let logprobs: CompletionLogprobs = serde_json::from_str(
+            r#"{
+                "tokens": ["\n", "positive"],
+                "token_logprobs": [-0.5, -0.25],
+                "top_logprobs": [{"\n": -0.5, " ": -1.2}, {"positive": -0.25, "neutral": -1.5}],
+                "text_offset": [12, 13]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            logprobs.iter().collect::<Vec<_>>(),
+            vec![("\n", Some(-0.5)), ("positive", Some(-0.25))]
+        );
+        assert_eq!(logprobs.total_logprob(), -0.75);
+        assert_eq!(
+            logprobs.top_logprobs.unwrap()[1].as_ref().unwrap()["neutral"],
+            -1.5
+        );
+        assert_eq!(logprobs.text_offset, vec![12, 13]);
+    }
+
+    #[test]
+    fn test_can_deserialize_chat_logprobs() {
+        let logprobs: ChatLogprobs = serde_json::from_str(
+            r#"{
+                "content": [
+                    {
+                        "token": "positive",
+                        "logprob": -0.25,
+                        "bytes": [112, 111, 115, 105, 116, 105, 118, 101],
+                        "top_logprobs": [
+                            {"token": "positive", "logprob": -0.25, "bytes": null},
+                            {"token": "neutral", "logprob": -1.5, "bytes": null}
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let token = logprobs.iter().next().unwrap();
+        assert_eq!(token.token, "positive");
+        assert_eq!(token.top_logprobs[1].token, "neutral");
+        assert_eq!(logprobs.total_logprob(), -0.25);
+        assert!((logprobs.probability() - (-0.25f32).exp()).abs() < f32::EPSILON);
+    }
+}
@@ -1,15 +1,34 @@
 //! # OpenAI
 
+mod audio;
+mod cache;
 mod config;
+mod credentials;
+mod deadline;
 mod error;
+mod image;
 mod input;
 mod kind;
+mod logprobs;
 mod model;
+mod moderation;
+mod partial_json;
 mod stream;
+mod stream_ext;
+mod telemetry;
 
+pub use audio::*;
+pub use cache::*;
 pub use config::*;
+pub use credentials::*;
+pub use deadline::*;
 pub use error::*;
+pub use image::*;
 pub use input::*;
 pub use kind::*;
+pub use logprobs::*;
 pub use model::*;
+pub use moderation::*;
+pub use partial_json::*;
 pub use stream::*;
+pub use stream_ext::*;
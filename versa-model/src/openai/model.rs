@@ -1,8 +1,13 @@
 //! This module contains implementations of OpenAI models.
 
 use super::{
-    ChatConfig, ChatMessage, ChatMessages, ChatModel, ChatModelStream, CompletionConfig,
-    CompletionModel, CompletionModelStream, ModelKind, OpenAIConfig,
+    telemetry::{CallSpan, StreamTelemetry},
+    CancellationToken, ChatConfig, ChatEventStream, ChatLogprobs, ChatMessage, ChatMessages,
+    ChatModel, ChatModelResponseStream, ChatModelStream, CompletionConfig, CompletionLogprobs,
+    CompletionModel, CompletionModelResponseStream, CompletionModelStream, CredentialChain,
+    CredentialProvider, Deadline, FileCredential, ImageModel, ModelKind, ModerationModel,
+    OpenAIConfig, ResponseCache, ResponseFormat, SpeechModel, StaticCredential, TranscriptionModel,
+    OPENAI_API_KEY_VAR, OPENAI_MODELS_URL,
 };
 use crate::{
     openai::{APIError, OpenAIError},
@@ -10,13 +15,15 @@ use crate::{
     ModelError,
 };
 use async_trait::async_trait;
-use reqwest::{header::AUTHORIZATION, Client};
-use reqwest_eventsource::RequestBuilderExt;
-use serde::{Deserialize, Serialize};
+use reqwest::{header::AUTHORIZATION, Client, RequestBuilder, Response};
+use reqwest_eventsource::{EventSource, RequestBuilderExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     collections::HashMap,
     env,
     fmt::{self, Debug, Formatter},
+    path::PathBuf,
+    time::Duration,
 };
 use versa_common::traits::Config;
 
@@ -26,6 +33,10 @@ use versa_common::traits::Config;
 
 pub type OpenAICompletionModel = OpenAI<CompletionModel>;
 pub type OpenAIChatModel = OpenAI<ChatModel>;
+pub type OpenAIImageModel = OpenAI<ImageModel>;
+pub type OpenAITranscriptionModel = OpenAI<TranscriptionModel>;
+pub type OpenAISpeechModel = OpenAI<SpeechModel>;
+pub type OpenAIModerationModel = OpenAI<ModerationModel>;
 pub type OpenAIModel = OpenAIChatModel;
 
 pub type CompletionModelResponse = ModelResponse<CompletionChoice>;
@@ -44,20 +55,55 @@ where
 {
     /// The configuration of the model.
     #[serde(flatten)]
-    config: M::Config,
+    pub(super) config: M::Config,
 
-    // OpenAI API key.
+    // Where the OpenAI API key is fetched from on each request.
     #[serde(skip)]
-    api_key: Option<String>,
+    pub(super) credentials: CredentialChain,
+
+    // Where chat and completion responses are cached, if anywhere.
+    #[serde(skip)]
+    pub(super) cache: Option<ResponseCache>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ModelResponse<T> {
     pub id: String,
     pub object: String,
     pub created: u64,
     pub model: String,
     pub choices: Vec<T>,
+    pub usage: Option<Usage>,
+}
+
+/// The number of tokens used by a request.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelListResponse {
+    pub object: String,
+    pub data: Vec<ModelListEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelListEntry {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub owned_by: String,
+}
+
+/// A model available to the API key, as returned by `OpenAI::list_models`.
+#[derive(Debug, Clone)]
+pub struct ModelInfo<M> {
+    pub model: M,
+    pub created: u64,
+    pub owned_by: String,
 }
 
 #[derive(Debug, Serialize, Default)]
@@ -82,18 +128,19 @@ pub struct CompletionBody {
     pub config: CompletionConfig,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ChatChoice {
     pub index: u64,
     pub message: ChatMessage,
+    pub logprobs: Option<ChatLogprobs>,
     pub finish_reason: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CompletionChoice {
     pub index: u64,
     pub text: String,
-    pub logprobs: Option<u8>,
+    pub logprobs: Option<CompletionLogprobs>,
     pub finish_reason: String,
 }
 
@@ -107,18 +154,143 @@ where
     M: ModelKind,
 {
     /// Creates a new OpenAI model with the given configuration.
+    ///
+    /// The API key is read from the `OPENAI_API_KEY` environment variable at request time unless
+    /// other credentials are set.
     pub fn with_config(config: M::Config) -> Self {
         Self {
             config,
-            api_key: Default::default(),
+            credentials: Default::default(),
+            cache: None,
         }
     }
 
-    /// Sets the API key.
+    /// Creates a new OpenAI model using the API key in the `OPENAI_API_KEY` environment variable.
+    ///
+    /// Unlike `default`, this fails immediately if the variable is not set.
+    pub fn from_env() -> Result<Self, OpenAIError> {
+        let api_key = env::var(OPENAI_API_KEY_VAR).map_err(|_| OpenAIError::MissingAPIKey)?;
+        Ok(Self::default().api_key(api_key))
+    }
+
+    /// Sets the API key. It takes precedence over every other credential provider.
     pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
-        self.api_key = Some(api_key.into());
+        self.credentials = self.credentials.with_first(StaticCredential::new(api_key));
+        self
+    }
+
+    /// Adds a file to read the API key from, after the existing credential providers.
+    pub fn api_key_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.credentials = self.credentials.with(FileCredential::new(path));
+        self
+    }
+
+    /// Adds a custom credential provider, after the existing ones.
+    pub fn credential_provider(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.credentials = self.credentials.with(provider);
         self
     }
+
+    /// Replaces the credential providers.
+    pub fn credentials(mut self, credentials: CredentialChain) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Caches chat and completion responses in the given cache.
+    pub fn cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Fetches the models available to the API key.
+    ///
+    /// Known ids are mapped onto the existing variants of `M`, every other id becomes a custom model.
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo<M>>, ModelError> {
+        let request = Client::new().get(OPENAI_MODELS_URL);
+        let response: ModelListResponse = self.send(request).await?;
+
+        Ok(response
+            .data
+            .into_iter()
+            .map(|entry| ModelInfo {
+                model: M::from_id(&entry.id),
+                created: entry.created,
+                owned_by: entry.owned_by,
+            })
+            .collect())
+    }
+
+    /// Returns the cache and key to use for a request body, if the call should be cached.
+    fn cache_key(
+        &self,
+        temperature: Option<f32>,
+        url: &str,
+        body: &impl Serialize,
+    ) -> Result<Option<(&ResponseCache, String)>, OpenAIError> {
+        match self.cache.as_ref() {
+            Some(cache) if cache.applies(temperature) => {
+                Ok(Some((cache, ResponseCache::key(url, body)?)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Posts the body to the given endpoint and parses the JSON response.
+    pub(super) async fn post<R>(&self, url: &str, body: &impl Serialize) -> Result<R, OpenAIError>
+    where
+        R: DeserializeOwned + Debug,
+    {
+        self.send(Client::new().post(url).json(body)).await
+    }
+
+    /// Posts the body to the given endpoint and opens an event source for the streamed response.
+    async fn post_eventsource(
+        &self,
+        url: &str,
+        body: &impl Serialize,
+    ) -> Result<EventSource, OpenAIError> {
+        Client::new()
+            .post(url)
+            .header(
+                AUTHORIZATION,
+                format!("Bearer {}", self.credentials.resolve().await?),
+            )
+            .json(body)
+            .eventsource()
+            .map_err(|_| OpenAIError::CannotCloneRequestError)
+    }
+
+    /// Authorizes and sends the request, then parses the JSON response.
+    pub(super) async fn send<R>(&self, request: RequestBuilder) -> Result<R, OpenAIError>
+    where
+        R: DeserializeOwned + Debug,
+    {
+        let response: R = self.send_raw(request).await?.json().await?;
+
+        #[cfg(feature = "log")]
+        log::debug!("response: {response:#?}");
+
+        Ok(response)
+    }
+
+    /// Authorizes and sends the request, returning the response as is if it succeeded.
+    pub(super) async fn send_raw(&self, request: RequestBuilder) -> Result<Response, OpenAIError> {
+        let response = request
+            .header(
+                AUTHORIZATION,
+                format!("Bearer {}", self.credentials.resolve().await?),
+            )
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error: APIError = response.json().await?;
+            return Err(OpenAIError::API(error));
+        }
+
+        Ok(response)
+    }
 }
 
 // TODO(nyprothegeek): Document the builder methods properly.
@@ -129,25 +301,19 @@ impl OpenAIChatModel {
         self
     }
 
-    /// Sets the suffix.
-    pub fn suffix(mut self, suffix: impl Into<String>) -> Self {
-        self.config.attributes.suffix = Some(suffix.into());
-        self
-    }
-
     /// Sets the max tokens.
     pub fn max_tokens(mut self, max_tokens: u16) -> Self {
         self.config.attributes.max_tokens = Some(max_tokens);
         self
     }
 
-    /// Sets the temperature.
+    /// Sets the temperature. Must be between 0 and 2.
     pub fn temperature(mut self, temperature: f32) -> Self {
         self.config.attributes.temperature = Some(temperature);
         self
     }
 
-    /// Sets the top p.
+    /// Sets the top p. Must be between 0 and 1.
     pub fn top_p(mut self, top_p: f32) -> Self {
         self.config.attributes.top_p = Some(top_p);
         self
@@ -159,37 +325,25 @@ impl OpenAIChatModel {
         self
     }
 
-    /// Sets the logprobs.
-    pub fn logprobs(mut self, logprobs: u8) -> Self {
-        self.config.attributes.logprobs = Some(logprobs);
-        self
-    }
-
-    /// Sets the echo.
-    pub fn echo(mut self, echo: bool) -> Self {
-        self.config.attributes.echo = Some(echo);
+    /// Sets up to 4 sequences where the model will stop generating further tokens.
+    pub fn stop(mut self, stop: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.attributes.stop = Some(stop.into_iter().map(Into::into).collect());
         self
     }
 
-    /// Sets the presence penalty.
+    /// Sets the presence penalty. Must be between -2 and 2.
     pub fn presence_penalty(mut self, presence_penalty: f32) -> Self {
         self.config.attributes.presence_penalty = Some(presence_penalty);
         self
     }
 
-    /// Sets the frequency penalty.
+    /// Sets the frequency penalty. Must be between -2 and 2.
     pub fn frequency_penalty(mut self, frequency_penalty: f32) -> Self {
         self.config.attributes.frequency_penalty = Some(frequency_penalty);
         self
     }
 
-    /// Sets the best of.
-    pub fn best_of(mut self, best_of: u8) -> Self {
-        self.config.attributes.best_of = Some(best_of);
-        self
-    }
-
-    /// Sets the logit bias.
+    /// Sets the logit bias. Each bias must be between -100 and 100.
     pub fn logit_bias(mut self, logit_bias: HashMap<u64, i8>) -> Self {
         self.config.attributes.logit_bias = Some(logit_bias);
         self
@@ -200,6 +354,42 @@ impl OpenAIChatModel {
         self.config.attributes.user = Some(user_token.into());
         self
     }
+
+    /// Sets the seed used for best-effort deterministic sampling.
+    pub fn seed(mut self, seed: i64) -> Self {
+        self.config.attributes.seed = Some(seed);
+        self
+    }
+
+    /// Sets the response format.
+    pub fn response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.config.attributes.response_format = Some(response_format);
+        self
+    }
+
+    /// Sets whether to return the log probabilities of the output tokens.
+    pub fn logprobs(mut self, logprobs: bool) -> Self {
+        self.config.attributes.logprobs = Some(logprobs);
+        self
+    }
+
+    /// Sets the number of most likely tokens to return at each position. Requires `logprobs`.
+    pub fn top_logprobs(mut self, top_logprobs: u8) -> Self {
+        self.config.attributes.top_logprobs = Some(top_logprobs);
+        self
+    }
+
+    /// Cancels calls, and the streams they return, when the token is triggered.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.config.cancellation = Some(token);
+        self
+    }
+
+    /// Fails calls with `ModelError::Timeout` if they take longer than `timeout` overall.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
 }
 
 // TODO(nyprothegeek): Document the builder methods properly.
@@ -222,13 +412,13 @@ impl OpenAICompletionModel {
         self
     }
 
-    /// Sets the temperature.
+    /// Sets the temperature. Must be between 0 and 2.
     pub fn temperature(mut self, temperature: f32) -> Self {
         self.config.attributes.temperature = Some(temperature);
         self
     }
 
-    /// Sets the top p.
+    /// Sets the top p. Must be between 0 and 1.
     pub fn top_p(mut self, top_p: f32) -> Self {
         self.config.attributes.top_p = Some(top_p);
         self
@@ -240,7 +430,7 @@ impl OpenAICompletionModel {
         self
     }
 
-    /// Sets the logprobs.
+    /// Sets the number of most likely tokens to return log probabilities for. At most 5.
     pub fn logprobs(mut self, logprobs: u8) -> Self {
         self.config.attributes.logprobs = Some(logprobs);
         self
@@ -252,13 +442,19 @@ impl OpenAICompletionModel {
         self
     }
 
-    /// Sets the presence penalty.
+    /// Sets up to 4 sequences where the model will stop generating further tokens.
+    pub fn stop(mut self, stop: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.attributes.stop = Some(stop.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets the presence penalty. Must be between -2 and 2.
     pub fn presence_penalty(mut self, presence_penalty: f32) -> Self {
         self.config.attributes.presence_penalty = Some(presence_penalty);
         self
     }
 
-    /// Sets the frequency penalty.
+    /// Sets the frequency penalty. Must be between -2 and 2.
     pub fn frequency_penalty(mut self, frequency_penalty: f32) -> Self {
         self.config.attributes.frequency_penalty = Some(frequency_penalty);
         self
@@ -270,7 +466,7 @@ impl OpenAICompletionModel {
         self
     }
 
-    /// Sets the logit bias.
+    /// Sets the logit bias. Each bias must be between -100 and 100.
     pub fn logit_bias(mut self, logit_bias: HashMap<u64, i8>) -> Self {
         self.config.attributes.logit_bias = Some(logit_bias);
         self
@@ -281,6 +477,18 @@ impl OpenAICompletionModel {
         self.config.attributes.user = Some(user_token.into());
         self
     }
+
+    /// Cancels calls, and the streams they return, when the token is triggered.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.config.cancellation = Some(token);
+        self
+    }
+
+    /// Fails calls with `ModelError::Timeout` if they take longer than `timeout` overall.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -319,46 +527,115 @@ where
 }
 
 #[async_trait(?Send)]
-impl Output<OpenAIChatModel> for String {
+impl Output<OpenAIChatModel> for ChatModelResponse {
     async fn from_call_with_config(
         input: impl Into<ChatMessages>,
         model: &OpenAIChatModel,
         config: ChatConfig,
     ) -> Result<Self, ModelError> {
-        let request = Client::new()
-            .post(model.config.get_url())
-            .header(
-                AUTHORIZATION,
-                format!(
-                    "Bearer {}",
-                    model.api_key.as_ref().ok_or(OpenAIError::MissingAPIKey)?
-                ),
-            )
-            .json(&ChatBody {
-                messages: input.into(),
-                config,
-                ..Default::default()
-            });
+        config.validate().map_err(OpenAIError::Validation)?;
 
-        let response = request.send().await.map_err(OpenAIError::Reqwest)?;
+        let mut deadline = Deadline::new(config.cancellation.as_ref(), config.timeout);
+        let messages = input.into();
+        if let Some(moderation) = config.moderation.clone() {
+            deadline.run(model.moderate(&messages, moderation)).await?;
+        }
 
-        if !response.status().is_success() {
-            let error: APIError = response.json().await.map_err(OpenAIError::Reqwest)?;
-            return Err(OpenAIError::API(error).into());
+        let temperature = config.attributes.temperature;
+        let span = CallSpan::new(&config.model, model.config.get_url(), false);
+        let body = ChatBody {
+            messages,
+            config,
+            ..Default::default()
+        };
+        span.record_request(&body);
+
+        let cache = model.cache_key(temperature, model.config.get_url(), &body)?;
+        if let Some((cache, key)) = &cache {
+            if let Some(response) = cache.get(key).await? {
+                return Ok(response);
+            }
         }
 
-        let response: ChatModelResponse = response.json().await.map_err(OpenAIError::Reqwest)?;
+        let result = deadline
+            .run(span.instrument(model.post(model.config.get_url(), &body)))
+            .await;
+        span.record_result(
+            &result,
+            |response: &Self| response.usage.as_ref(),
+            |response| response.choices.first().map(|c| c.finish_reason.as_str()),
+        );
+
+        let response = result?;
+        if let Some((cache, key)) = &cache {
+            cache.put_or_log(key, &response).await;
+        }
 
-        #[cfg(feature = "log")]
-        log::debug!("response: {response:#?}");
+        Ok(response)
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAICompletionModel> for CompletionModelResponse {
+    async fn from_call_with_config(
+        input: impl Into<String>,
+        model: &OpenAICompletionModel,
+        config: CompletionConfig,
+    ) -> Result<Self, ModelError> {
+        config.validate().map_err(OpenAIError::Validation)?;
+
+        let mut deadline = Deadline::new(config.cancellation.as_ref(), config.timeout);
+        let temperature = config.attributes.temperature;
+        let span = CallSpan::new(&config.model, model.config.get_url(), false);
+        let body = CompletionBody {
+            prompt: input.into(),
+            config,
+            ..Default::default()
+        };
+        span.record_request(&body);
+
+        let cache = model.cache_key(temperature, model.config.get_url(), &body)?;
+        if let Some((cache, key)) = &cache {
+            if let Some(response) = cache.get(key).await? {
+                return Ok(response);
+            }
+        }
+
+        let result = deadline
+            .run(span.instrument(model.post(model.config.get_url(), &body)))
+            .await;
+        span.record_result(
+            &result,
+            |response: &Self| response.usage.as_ref(),
+            |response| response.choices.first().map(|c| c.finish_reason.as_str()),
+        );
+
+        let response = result?;
+        if let Some((cache, key)) = &cache {
+            cache.put_or_log(key, &response).await;
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAIChatModel> for String {
+    async fn from_call_with_config(
+        input: impl Into<ChatMessages>,
+        model: &OpenAIChatModel,
+        config: ChatConfig,
+    ) -> Result<Self, ModelError> {
+        let response = ChatModelResponse::from_call_with_config(input, model, config).await?;
 
         Ok(response
             .choices
-            .get(0)
+            .into_iter()
+            .next()
             .ok_or(OpenAIError::CompletionMissing)?
             .message
             .content
-            .clone())
+            .into())
     }
 }
 
@@ -369,40 +646,95 @@ impl Output<OpenAICompletionModel> for String {
         model: &OpenAICompletionModel,
         config: CompletionConfig,
     ) -> Result<Self, ModelError> {
-        let request = Client::new()
-            .post(model.config.get_url())
-            .header(
-                AUTHORIZATION,
-                format!(
-                    "Bearer {}",
-                    model.api_key.as_ref().ok_or(OpenAIError::MissingAPIKey)?
-                ),
-            )
-            .json(&CompletionBody {
-                prompt: input.into(),
-                config,
-                ..Default::default()
-            });
+        let response = CompletionModelResponse::from_call_with_config(input, model, config).await?;
 
-        let response = request.send().await.map_err(OpenAIError::Reqwest)?;
+        Ok(response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or(OpenAIError::CompletionMissing)?
+            .text)
+    }
+}
 
-        if !response.status().is_success() {
-            let error: APIError = response.json().await.map_err(OpenAIError::Reqwest)?;
-            return Err(OpenAIError::API(error).into());
+#[async_trait(?Send)]
+impl Output<OpenAIChatModel> for ChatModelResponseStream {
+    async fn from_call_with_config(
+        input: impl Into<ChatMessages>,
+        model: &OpenAIChatModel,
+        config: ChatConfig,
+    ) -> Result<Self, ModelError> {
+        config.validate().map_err(OpenAIError::Validation)?;
+
+        let mut deadline = Deadline::new(config.cancellation.as_ref(), config.timeout);
+        let messages = input.into();
+        if let Some(moderation) = config.moderation.clone() {
+            deadline.run(model.moderate(&messages, moderation)).await?;
         }
 
-        let response: CompletionModelResponse =
-            response.json().await.map_err(OpenAIError::Reqwest)?;
+        let temperature = config.attributes.temperature;
+        let span = CallSpan::new(&config.model, model.config.get_url(), true);
+        let mut body = ChatBody {
+            messages,
+            stream: None,
+            config,
+        };
 
-        #[cfg(feature = "log")]
-        log::debug!("response: {response:#?}");
+        // Streamed calls share the cache entries of regular calls.
+        if let Some((cache, key)) = model.cache_key(temperature, model.config.get_url(), &body)? {
+            if let Some(response) = cache.get(&key).await? {
+                return Ok(ChatModelResponseStream::replay_response(response));
+            }
+        }
 
-        Ok(response
-            .choices
-            .get(0)
-            .ok_or(OpenAIError::CompletionMissing)?
-            .text
-            .clone())
+        body.stream = Some(true);
+        span.record_request(&body);
+
+        let event_src = deadline
+            .run(span.instrument(model.post_eventsource(model.config.get_url(), &body)))
+            .await?;
+
+        Ok(ChatModelResponseStream::new(event_src)
+            .with_telemetry(StreamTelemetry::new(span))
+            .with_deadline(deadline))
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAICompletionModel> for CompletionModelResponseStream {
+    async fn from_call_with_config(
+        input: impl Into<String>,
+        model: &OpenAICompletionModel,
+        config: CompletionConfig,
+    ) -> Result<Self, ModelError> {
+        config.validate().map_err(OpenAIError::Validation)?;
+
+        let mut deadline = Deadline::new(config.cancellation.as_ref(), config.timeout);
+        let temperature = config.attributes.temperature;
+        let span = CallSpan::new(&config.model, model.config.get_url(), true);
+        let mut body = CompletionBody {
+            prompt: input.into(),
+            stream: None,
+            config,
+        };
+
+        // Streamed calls share the cache entries of regular calls.
+        if let Some((cache, key)) = model.cache_key(temperature, model.config.get_url(), &body)? {
+            if let Some(response) = cache.get(&key).await? {
+                return Ok(CompletionModelResponseStream::replay_response(response));
+            }
+        }
+
+        body.stream = Some(true);
+        span.record_request(&body);
+
+        let event_src = deadline
+            .run(span.instrument(model.post_eventsource(model.config.get_url(), &body)))
+            .await?;
+
+        Ok(CompletionModelResponseStream::new(event_src)
+            .with_telemetry(StreamTelemetry::new(span))
+            .with_deadline(deadline))
     }
 }
 
@@ -413,26 +745,20 @@ impl Output<OpenAIChatModel> for ChatModelStream {
         model: &OpenAIChatModel,
         config: ChatConfig,
     ) -> Result<Self, ModelError> {
-        let request = Client::new()
-            .post(model.config.get_url())
-            .header(
-                AUTHORIZATION,
-                format!(
-                    "Bearer {}",
-                    model.api_key.as_ref().ok_or(OpenAIError::MissingAPIKey)?
-                ),
-            )
-            .json(&ChatBody {
-                messages: input.into(),
-                stream: Some(true),
-                config,
-            });
-
-        let event_src = request
-            .eventsource()
-            .map_err(|_| OpenAIError::CannotCloneRequestError)?;
+        let stream = ChatModelResponseStream::from_call_with_config(input, model, config).await?;
+        Ok(ChatModelStream::from(stream))
+    }
+}
 
-        Ok(ChatModelStream::new(event_src))
+#[async_trait(?Send)]
+impl Output<OpenAIChatModel> for ChatEventStream {
+    async fn from_call_with_config(
+        input: impl Into<ChatMessages>,
+        model: &OpenAIChatModel,
+        config: ChatConfig,
+    ) -> Result<Self, ModelError> {
+        let stream = ChatModelResponseStream::from_call_with_config(input, model, config).await?;
+        Ok(ChatEventStream::new(stream))
     }
 }
 
@@ -443,26 +769,9 @@ impl Output<OpenAICompletionModel> for CompletionModelStream {
         model: &OpenAICompletionModel,
         config: CompletionConfig,
     ) -> Result<Self, ModelError> {
-        let request = Client::new()
-            .post(model.config.get_url())
-            .header(
-                AUTHORIZATION,
-                format!(
-                    "Bearer {}",
-                    model.api_key.as_ref().ok_or(OpenAIError::MissingAPIKey)?
-                ),
-            )
-            .json(&CompletionBody {
-                prompt: input.into(),
-                stream: Some(true),
-                config,
-            });
-
-        let event_src = request
-            .eventsource()
-            .map_err(|_| OpenAIError::CannotCloneRequestError)?;
-
-        Ok(CompletionModelStream::new(event_src))
+        let stream =
+            CompletionModelResponseStream::from_call_with_config(input, model, config).await?;
+        Ok(CompletionModelStream::from(stream))
     }
 }
 
@@ -471,10 +780,7 @@ where
     M: ModelKind,
 {
     fn default() -> Self {
-        Self {
-            config: Default::default(),
-            api_key: Some(env::var("OPENAI_API_KEY").unwrap()),
-        }
+        Self::with_config(Default::default())
     }
 }
 
@@ -502,26 +808,37 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn models_are_send_and_sync() {
+        fn _assert<T: Send + Sync>() {}
+
+        _assert::<OpenAI<ChatModel>>();
+        _assert::<OpenAI<CompletionModel>>();
+        _assert::<OpenAI<ImageModel>>();
+        _assert::<OpenAI<TranscriptionModel>>();
+        _assert::<OpenAI<SpeechModel>>();
+        _assert::<OpenAI<ModerationModel>>();
+    }
+
     #[test]
     fn language_model_config_defaults_are_correct() {
         utils::load_env(Env::Test);
         let model = OpenAIModel::default();
 
         assert_eq!(model.config.model, ChatModel::GPT3_5Turbo);
-        assert_eq!(model.config.attributes.suffix, None);
         assert_eq!(model.config.attributes.max_tokens, None);
         assert_eq!(model.config.attributes.temperature, None);
         assert_eq!(model.config.attributes.top_p, None);
         assert_eq!(model.config.attributes.n, None);
-        assert_eq!(model.config.attributes.stream, None);
-        assert_eq!(model.config.attributes.logprobs, None);
-        assert_eq!(model.config.attributes.echo, None);
         assert_eq!(model.config.attributes.stop, None);
         assert_eq!(model.config.attributes.presence_penalty, None);
         assert_eq!(model.config.attributes.frequency_penalty, None);
-        assert_eq!(model.config.attributes.best_of, None);
         assert_eq!(model.config.attributes.logit_bias, None);
         assert_eq!(model.config.attributes.user, None);
+        assert_eq!(model.config.attributes.seed, None);
+        assert_eq!(model.config.attributes.response_format, None);
+        assert_eq!(model.config.attributes.logprobs, None);
+        assert_eq!(model.config.attributes.top_logprobs, None);
 
         let model = OpenAICompletionModel::default();
 
@@ -531,7 +848,6 @@ mod tests {
         assert_eq!(model.config.attributes.temperature, None);
         assert_eq!(model.config.attributes.top_p, None);
         assert_eq!(model.config.attributes.n, None);
-        assert_eq!(model.config.attributes.stream, None);
         assert_eq!(model.config.attributes.logprobs, None);
         assert_eq!(model.config.attributes.echo, None);
         assert_eq!(model.config.attributes.stop, None);
@@ -0,0 +1,471 @@
+//! This module contains the implementation of the OpenAI moderation model.
+
+use super::{
+    telemetry::CallSpan, ChatMessages, ChatRole, ModerationConfig, ModerationInput,
+    ModerationModel, OpenAIChatModel, OpenAIConfig, OpenAIError, OpenAIModerationModel,
+};
+use crate::{Model, ModelError, Output};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use strum_macros::Display;
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Serialize)]
+pub struct ModerationBody {
+    pub input: Vec<String>,
+
+    #[serde(flatten)]
+    pub config: ModerationConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<ModerationResult>,
+}
+
+/// The moderation verdict for one input.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    pub categories: ModerationCategories,
+    pub category_scores: ModerationCategoryScores,
+}
+
+/// Whether each category was flagged. Categories missing from the response are not flagged.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ModerationCategories {
+    pub hate: bool,
+
+    #[serde(rename = "hate/threatening")]
+    pub hate_threatening: bool,
+
+    pub harassment: bool,
+
+    #[serde(rename = "harassment/threatening")]
+    pub harassment_threatening: bool,
+
+    #[serde(rename = "self-harm")]
+    pub self_harm: bool,
+
+    #[serde(rename = "self-harm/intent")]
+    pub self_harm_intent: bool,
+
+    #[serde(rename = "self-harm/instructions")]
+    pub self_harm_instructions: bool,
+
+    pub sexual: bool,
+
+    #[serde(rename = "sexual/minors")]
+    pub sexual_minors: bool,
+
+    pub violence: bool,
+
+    #[serde(rename = "violence/graphic")]
+    pub violence_graphic: bool,
+
+    /// Categories that are not known yet, by name.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, bool>,
+}
+
+/// The confidence score of each category, between 0 and 1. Categories missing from the response
+/// score 0.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ModerationCategoryScores {
+    pub hate: f32,
+
+    #[serde(rename = "hate/threatening")]
+    pub hate_threatening: f32,
+
+    pub harassment: f32,
+
+    #[serde(rename = "harassment/threatening")]
+    pub harassment_threatening: f32,
+
+    #[serde(rename = "self-harm")]
+    pub self_harm: f32,
+
+    #[serde(rename = "self-harm/intent")]
+    pub self_harm_intent: f32,
+
+    #[serde(rename = "self-harm/instructions")]
+    pub self_harm_instructions: f32,
+
+    pub sexual: f32,
+
+    #[serde(rename = "sexual/minors")]
+    pub sexual_minors: f32,
+
+    pub violence: f32,
+
+    #[serde(rename = "violence/graphic")]
+    pub violence_graphic: f32,
+
+    /// The scores of categories that are not known yet, by name.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, f32>,
+}
+
+/// A moderation category.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Display)]
+pub enum ModerationCategory {
+    #[strum(serialize = "hate")]
+    #[serde(rename = "hate")]
+    Hate,
+
+    #[strum(serialize = "hate/threatening")]
+    #[serde(rename = "hate/threatening")]
+    HateThreatening,
+
+    #[strum(serialize = "harassment")]
+    #[serde(rename = "harassment")]
+    Harassment,
+
+    #[strum(serialize = "harassment/threatening")]
+    #[serde(rename = "harassment/threatening")]
+    HarassmentThreatening,
+
+    #[strum(serialize = "self-harm")]
+    #[serde(rename = "self-harm")]
+    SelfHarm,
+
+    #[strum(serialize = "self-harm/intent")]
+    #[serde(rename = "self-harm/intent")]
+    SelfHarmIntent,
+
+    #[strum(serialize = "self-harm/instructions")]
+    #[serde(rename = "self-harm/instructions")]
+    SelfHarmInstructions,
+
+    #[strum(serialize = "sexual")]
+    #[serde(rename = "sexual")]
+    Sexual,
+
+    #[strum(serialize = "sexual/minors")]
+    #[serde(rename = "sexual/minors")]
+    SexualMinors,
+
+    #[strum(serialize = "violence")]
+    #[serde(rename = "violence")]
+    Violence,
+
+    #[strum(serialize = "violence/graphic")]
+    #[serde(rename = "violence/graphic")]
+    ViolenceGraphic,
+
+    /// A category that is not known yet.
+    #[strum(default)]
+    #[serde(untagged)]
+    Other(String),
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+// TODO(nyprothegeek): Document the builder methods properly.
+impl OpenAIModerationModel {
+    /// Sets the model.
+    pub fn model(mut self, model: ModerationModel) -> Self {
+        self.config.model = model;
+        self
+    }
+}
+
+impl OpenAIChatModel {
+    /// Checks the user messages with the given moderation model before every call.
+    ///
+    /// Calls with flagged messages fail with `ModelError::Flagged` without reaching the chat model.
+    pub fn moderation(mut self, model: ModerationModel) -> Self {
+        self.config.moderation = Some(model);
+        self
+    }
+
+    /// Fails with `ModelError::Flagged` if any of the user messages is flagged by the moderation model.
+    pub(super) async fn moderate(
+        &self,
+        messages: &ChatMessages,
+        model: ModerationModel,
+    ) -> Result<(), ModelError> {
+        let input: Vec<String> = messages
+            .iter()
+            .filter(|message| matches!(message.role, ChatRole::User))
+            .map(|message| message.content.text())
+            .collect();
+
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        let moderator = OpenAIModerationModel::with_config(ModerationConfig { model })
+            .credentials(self.credentials.clone());
+        let response: ModerationResponse = moderator.prompt(input).await?;
+        check_flagged(&response.results)
+    }
+}
+
+impl ModerationCategories {
+    /// Returns the flagged categories.
+    pub fn flagged(&self) -> Vec<ModerationCategory> {
+        [
+            (self.hate, ModerationCategory::Hate),
+            (self.hate_threatening, ModerationCategory::HateThreatening),
+            (self.harassment, ModerationCategory::Harassment),
+            (
+                self.harassment_threatening,
+                ModerationCategory::HarassmentThreatening,
+            ),
+            (self.self_harm, ModerationCategory::SelfHarm),
+            (self.self_harm_intent, ModerationCategory::SelfHarmIntent),
+            (
+                self.self_harm_instructions,
+                ModerationCategory::SelfHarmInstructions,
+            ),
+            (self.sexual, ModerationCategory::Sexual),
+            (self.sexual_minors, ModerationCategory::SexualMinors),
+            (self.violence, ModerationCategory::Violence),
+            (self.violence_graphic, ModerationCategory::ViolenceGraphic),
+        ]
+        .into_iter()
+        .filter_map(|(flagged, category)| flagged.then_some(category))
+        .chain(
+            self.other
+                .iter()
+                .filter(|(_, flagged)| **flagged)
+                .map(|(name, _)| ModerationCategory::Other(name.clone())),
+        )
+        .collect()
+    }
+}
+
+impl ModerationCategoryScores {
+    /// Returns the score of the given category.
+    pub fn get(&self, category: &ModerationCategory) -> f32 {
+        match category {
+            ModerationCategory::Hate => self.hate,
+            ModerationCategory::HateThreatening => self.hate_threatening,
+            ModerationCategory::Harassment => self.harassment,
+            ModerationCategory::HarassmentThreatening => self.harassment_threatening,
+            ModerationCategory::SelfHarm => self.self_harm,
+            ModerationCategory::SelfHarmIntent => self.self_harm_intent,
+            ModerationCategory::SelfHarmInstructions => self.self_harm_instructions,
+            ModerationCategory::Sexual => self.sexual,
+            ModerationCategory::SexualMinors => self.sexual_minors,
+            ModerationCategory::Violence => self.violence,
+            ModerationCategory::ViolenceGraphic => self.violence_graphic,
+            ModerationCategory::Other(name) => self.other.get(name).copied().unwrap_or_default(),
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Fails with `ModelError::Flagged` if any result is flagged, even if none of its categories are
+/// set, listing the flagged categories.
+fn check_flagged(results: &[ModerationResult]) -> Result<(), ModelError> {
+    let mut flagged = false;
+    let mut categories = vec![];
+    for result in results.iter().filter(|result| result.flagged) {
+        flagged = true;
+        for category in result.categories.flagged() {
+            if !categories.contains(&category) {
+                categories.push(category);
+            }
+        }
+    }
+
+    if !flagged {
+        return Ok(());
+    }
+
+    Err(ModelError::Flagged(categories))
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+#[async_trait(?Send)]
+impl Output<OpenAIModerationModel> for ModerationResponse {
+    async fn from_call_with_config(
+        input: impl Into<ModerationInput>,
+        model: &OpenAIModerationModel,
+        config: ModerationConfig,
+    ) -> Result<Self, ModelError> {
+        let span = CallSpan::new(&config.model, config.get_url(), false);
+        let url = config.get_url().to_string();
+        let body = ModerationBody {
+            input: input.into().into(),
+            config,
+        };
+        span.record_request(&body);
+
+        let result = span.instrument(model.post(&url, &body)).await;
+        span.record_result(&result, |_| None, |_| None);
+
+        Ok(result?)
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAIModerationModel> for Vec<ModerationResult> {
+    async fn from_call_with_config(
+        input: impl Into<ModerationInput>,
+        model: &OpenAIModerationModel,
+        config: ModerationConfig,
+    ) -> Result<Self, ModelError> {
+        let response = ModerationResponse::from_call_with_config(input, model, config).await?;
+        Ok(response.results)
+    }
+}
+
+#[async_trait(?Send)]
+impl Output<OpenAIModerationModel> for ModerationResult {
+    async fn from_call_with_config(
+        input: impl Into<ModerationInput>,
+        model: &OpenAIModerationModel,
+        config: ModerationConfig,
+    ) -> Result<Self, ModelError> {
+        let response = ModerationResponse::from_call_with_config(input, model, config).await?;
+
+        Ok(response
+            .results
+            .into_iter()
+            .next()
+            .ok_or(OpenAIError::CompletionMissing)?)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_deserialize_moderation_response() {
+        let response: ModerationResponse = serde_json::from_str(
+            r#"{
+                "id": "modr-XXXXX",
+                "model": "text-moderation-005",
+                "results": [
+                    {
+                        "flagged": true,
+                        "categories": {
+                            "sexual": false,
+                            "hate": false,
+                            "harassment": true,
+                            "self-harm": false,
+                            "sexual/minors": false,
+                            "hate/threatening": false,
+                            "violence/graphic": false,
+                            "self-harm/intent": false,
+                            "self-harm/instructions": false,
+                            "harassment/threatening": true,
+                            "violence": false
+                        },
+                        "category_scores": {
+                            "sexual": 0.01,
+                            "hate": 0.2,
+                            "harassment": 0.9,
+                            "self-harm": 0.0,
+                            "sexual/minors": 0.0,
+                            "hate/threatening": 0.0,
+                            "violence/graphic": 0.0,
+                            "self-harm/intent": 0.0,
+                            "self-harm/instructions": 0.0,
+                            "harassment/threatening": 0.7,
+                            "violence": 0.1
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let result = &response.results[0];
+        assert_eq!(
+            result.categories.flagged(),
+            vec![
+                ModerationCategory::Harassment,
+                ModerationCategory::HarassmentThreatening
+            ]
+        );
+        assert_eq!(
+            result
+                .category_scores
+                .get(&ModerationCategory::HarassmentThreatening),
+            0.7
+        );
+        assert_eq!(
+            ModerationCategory::SelfHarmIntent.to_string(),
+            "self-harm/intent"
+        );
+    }
+
+    #[test]
+    fn test_missing_categories_default_to_unflagged() {
+        let result: ModerationResult = serde_json::from_str(
+            r#"{
+                "flagged": true,
+                "categories": {"hate": true},
+                "category_scores": {"hate": 0.8}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(result.categories.flagged(), vec![ModerationCategory::Hate]);
+        assert_eq!(
+            result.category_scores.get(&ModerationCategory::Violence),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_flagged_results_fail_with_unknown_categories() {
+        let results: Vec<ModerationResult> = serde_json::from_str(
+            r#"[
+                {
+                    "flagged": false,
+                    "categories": {"hate": false},
+                    "category_scores": {"hate": 0.1}
+                },
+                {
+                    "flagged": true,
+                    "categories": {"hate": false, "illicit": true},
+                    "category_scores": {"hate": 0.0, "illicit": 0.9}
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        let category = ModerationCategory::Other("illicit".into());
+        assert_eq!(category.to_string(), "illicit");
+        assert_eq!(results[1].category_scores.get(&category), 0.9);
+        assert!(matches!(
+            check_flagged(&results),
+            Err(ModelError::Flagged(categories)) if categories == [category]
+        ));
+
+        // A flagged result without any category set still fails.
+        let mut unexplained = results[1].clone();
+        unexplained.categories.other.clear();
+        assert!(matches!(
+            check_flagged(&[unexplained]),
+            Err(ModelError::Flagged(categories)) if categories.is_empty()
+        ));
+        assert!(check_flagged(&results[..1]).is_ok());
+    }
+}
@@ -0,0 +1,483 @@
+//! Incremental parsing of JSON that is still being generated.
+
+use super::OpenAIError;
+use futures::{ready, Stream};
+use pin_project_lite::pin_project;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number, Value};
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+pin_project! {
+    /// A stream of progressively more complete snapshots of the JSON document being streamed.
+    ///
+    /// A snapshot is only yielded when it differs from the previous one. Snapshots that cannot be
+    /// deserialized into `T` yet are skipped, so typed snapshots should use `Option` fields.
+    pub struct PartialJson<S, T = Value> {
+        #[pin]
+        inner: S,
+        buffer: String,
+        last: Option<Value>,
+        done: bool,
+        output: PhantomData<T>,
+    }
+}
+
+/// A tolerant recursive descent parser over a JSON prefix.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+/// The outcome of parsing a value from a prefix.
+enum Parsed {
+    /// The value is complete.
+    Complete(Value),
+
+    /// The input ended inside the value; this is what has been generated so far.
+    Partial(Value),
+
+    /// The input ended before anything meaningful of the value was generated.
+    Missing,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Parses a possibly unterminated JSON document into the most complete value it describes so far.
+///
+/// Unterminated strings, arrays and objects are closed, while object keys without a value yet and
+/// incomplete literals such as `tru` are left out. Any text before the first `{` or `[`, like a
+/// markdown code fence, is ignored. Returns `None` if no value has started yet or the text is not
+/// valid JSON.
+pub fn parse_partial_json(text: &str) -> Option<Value> {
+    let start = text.find(['{', '['])?;
+    let mut parser = Parser {
+        text: &text[start..],
+        pos: 0,
+    };
+
+    match parser.value()? {
+        Parsed::Complete(value) | Parsed::Partial(value) => Some(value),
+        Parsed::Missing => None,
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl<S, T> PartialJson<S, T> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self {
+            inner,
+            buffer: String::new(),
+            last: None,
+            done: false,
+            output: PhantomData,
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Returns `None` if the text is not valid JSON.
+    fn value(&mut self) -> Option<Parsed> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Some(Parsed::Missing),
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Some(match self.string()? {
+                (value, true) => Parsed::Complete(Value::String(value)),
+                (value, false) => Parsed::Partial(Value::String(value)),
+            }),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => None,
+        }
+    }
+
+    fn object(&mut self) -> Option<Parsed> {
+        self.pos += 1;
+        let mut map = Map::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Some(Parsed::Partial(Value::Object(map))),
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Some(Parsed::Complete(Value::Object(map)));
+                }
+                Some(b'"') => {}
+                Some(_) => return None,
+            }
+
+            let (key, complete) = self.string()?;
+            self.skip_whitespace();
+            if !complete || self.peek().is_none() {
+                return Some(Parsed::Partial(Value::Object(map)));
+            }
+
+            if self.peek() != Some(b':') {
+                return None;
+            }
+
+            self.pos += 1;
+            match self.value()? {
+                Parsed::Complete(value) => {
+                    map.insert(key, value);
+                }
+                Parsed::Partial(value) => {
+                    map.insert(key, value);
+                    return Some(Parsed::Partial(Value::Object(map)));
+                }
+                Parsed::Missing => return Some(Parsed::Partial(Value::Object(map))),
+            }
+
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Some(Parsed::Partial(Value::Object(map))),
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Some(Parsed::Complete(Value::Object(map)));
+                }
+                Some(_) => return None,
+            }
+        }
+    }
+
+    fn array(&mut self) -> Option<Parsed> {
+        self.pos += 1;
+        let mut items = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b']') {
+                self.pos += 1;
+                return Some(Parsed::Complete(Value::Array(items)));
+            }
+
+            match self.value()? {
+                Parsed::Complete(value) => items.push(value),
+                Parsed::Partial(value) => {
+                    items.push(value);
+                    return Some(Parsed::Partial(Value::Array(items)));
+                }
+                Parsed::Missing => return Some(Parsed::Partial(Value::Array(items))),
+            }
+
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Some(Parsed::Partial(Value::Array(items))),
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Some(Parsed::Complete(Value::Array(items)));
+                }
+                Some(_) => return None,
+            }
+        }
+    }
+
+    /// Parses a string, returning whether its closing quote was reached.
+    fn string(&mut self) -> Option<(String, bool)> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let bytes = self.text.as_bytes();
+        while self.pos < bytes.len() {
+            match bytes[self.pos] {
+                b'"' => {
+                    self.pos += 1;
+                    let value = serde_json::from_str(&self.text[start..self.pos]).ok()?;
+                    return Some((value, true));
+                }
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+
+        self.pos = bytes.len();
+        let raw = trim_incomplete_escape(&self.text[start + 1..])?;
+        let value = serde_json::from_str(&format!("\"{raw}\"")).ok()?;
+        Some((value, false))
+    }
+
+    fn literal(&mut self, literal: &str, value: Value) -> Option<Parsed> {
+        let rest = &self.text[self.pos..];
+        if rest.starts_with(literal) {
+            self.pos += literal.len();
+            return Some(Parsed::Complete(value));
+        }
+
+        if literal.starts_with(rest) {
+            self.pos = self.text.len();
+            return Some(Parsed::Missing);
+        }
+
+        None
+    }
+
+    fn number(&mut self) -> Option<Parsed> {
+        let start = self.pos;
+        while matches!(
+            self.peek(),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.pos += 1;
+        }
+
+        let number = serde_json::from_str::<Number>(&self.text[start..self.pos]);
+        match (number, self.peek()) {
+            (Ok(number), Some(_)) => Some(Parsed::Complete(Value::Number(number))),
+            // More digits may still follow, so the number is not known yet.
+            (_, None) => Some(Parsed::Missing),
+            (Err(_), Some(_)) => None,
+        }
+    }
+}
+
+/// Drops a trailing escape sequence that has not been fully generated yet, or returns `None` if
+/// the escape can never become valid.
+fn trim_incomplete_escape(raw: &str) -> Option<&str> {
+    let backslash = match raw.rfind('\\') {
+        Some(backslash) => backslash,
+        None => return Some(raw),
+    };
+
+    // An even number of backslashes before this one means it is itself escaped.
+    let preceding = raw[..backslash]
+        .bytes()
+        .rev()
+        .take_while(|b| *b == b'\\')
+        .count();
+    if preceding % 2 == 1 {
+        return Some(raw);
+    }
+
+    let escape = &raw[backslash + 1..];
+    let complete = match escape.as_bytes().first() {
+        None => false,
+        Some(b'u') => {
+            // Checking the bytes first keeps the slice below on character boundaries.
+            let hex = &escape.as_bytes()[1..];
+            if !hex.iter().take(4).all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+
+            hex.len() >= 4 && !is_high_surrogate(&escape[1..5])
+        }
+        Some(_) => true,
+    };
+
+    if complete {
+        Some(raw)
+    } else {
+        Some(&raw[..backslash])
+    }
+}
+
+/// Whether the escaped code unit is the first half of a surrogate pair, whose second half has not
+/// been generated yet.
+fn is_high_surrogate(hex: &str) -> bool {
+    u16::from_str_radix(hex, 16).map_or(false, |unit| (0xD800..0xDC00).contains(&unit))
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl<S, T> Stream for PartialJson<S, T>
+where
+    S: Stream<Item = Result<String, OpenAIError>>,
+    T: DeserializeOwned,
+{
+    type Item = Result<T, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if *this.done {
+                return Poll::Ready(None);
+            }
+
+            match ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => this.buffer.push_str(&chunk),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    *this.done = true;
+
+                    // Make sure the finished document is valid and matches `T`, even if its last
+                    // snapshot was already yielded.
+                    let value = parse_partial_json(this.buffer).unwrap_or_default();
+                    if this.last.as_ref() == Some(&value) && T::deserialize(&value).is_ok() {
+                        return Poll::Ready(None);
+                    }
+
+                    let start = this.buffer.find(['{', '[']).unwrap_or_default();
+                    let end = this.buffer.rfind(['}', ']']).map_or(0, |end| end + 1);
+                    let json = this.buffer.get(start..end).unwrap_or_default();
+                    return Poll::Ready(Some(serde_json::from_str(json).map_err(Into::into)));
+                }
+            }
+
+            let value = match parse_partial_json(this.buffer) {
+                Some(value) if this.last.as_ref() != Some(&value) => value,
+                _ => continue,
+            };
+
+            if let Ok(output) = T::deserialize(&value) {
+                *this.last = Some(value);
+                return Poll::Ready(Some(Ok(output)));
+            }
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openai::TextStreamExt;
+    use futures::{stream, StreamExt};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[test]
+    fn test_parses_partial_json() {
+        assert_eq!(parse_partial_json(""), None);
+        assert_eq!(parse_partial_json("```json\n{"), Some(json!({})));
+        assert_eq!(parse_partial_json(r#"{"na"#), Some(json!({})));
+        assert_eq!(
+            parse_partial_json(r#"{"name": "Al"#),
+            Some(json!({"name": "Al"}))
+        );
+        assert_eq!(
+            parse_partial_json(r#"{"name": "Alice", "tags": ["a", "b"#),
+            Some(json!({"name": "Alice", "tags": ["a", "b"]}))
+        );
+        assert_eq!(
+            parse_partial_json(r#"{"ok": tr"#),
+            Some(json!({})),
+            "incomplete literals are left out"
+        );
+        assert_eq!(
+            parse_partial_json(r#"{"age": 4"#),
+            Some(json!({})),
+            "numbers that may still grow are left out"
+        );
+        assert_eq!(parse_partial_json(r#"{"age": 4 "#), Some(json!({"age": 4})));
+        assert_eq!(
+            parse_partial_json(r#"{"age": -"#),
+            Some(json!({})),
+            "incomplete numbers are left out"
+        );
+        assert_eq!(
+            parse_partial_json(r#"[{"a": 1}, {"b": "x\"y\u00"#),
+            Some(json!([{"a": 1}, {"b": "x\"y"}]))
+        );
+        assert_eq!(
+            parse_partial_json(r#"{"a": 1} trailing"#),
+            Some(json!({"a": 1}))
+        );
+        assert_eq!(parse_partial_json(r#"{"a" 1}"#), None);
+    }
+
+    #[test]
+    fn test_trims_incomplete_escapes() {
+        assert_eq!(trim_incomplete_escape(r"ab\"), Some("ab"));
+        assert_eq!(trim_incomplete_escape(r"ab\\"), Some(r"ab\\"));
+        assert_eq!(trim_incomplete_escape(r"ab\u00e"), Some("ab"));
+        assert_eq!(trim_incomplete_escape(r"abé"), Some(r"abé"));
+        assert_eq!(trim_incomplete_escape(r"ab\ud83d"), Some("ab"));
+        assert_eq!(trim_incomplete_escape(r"ab\u000é"), None);
+        assert_eq!(trim_incomplete_escape(r"ab\u0é"), None);
+        assert_eq!(parse_partial_json(r#"{"a": "\u000é"#), None);
+    }
+
+    #[tokio::test]
+    async fn test_streams_typed_snapshots() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Person {
+            name: Option<String>,
+            age: Option<u32>,
+        }
+
+        let chunks = ["{\"na", "me\": \"Bo", "b\", ", "\"age\": 4", "2}"];
+        let snapshots: Vec<Person> = stream::iter(chunks.map(|chunk| Ok(chunk.to_string())))
+            .partial_json::<Person>()
+            .map(|snapshot| snapshot.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            snapshots,
+            vec![
+                Person {
+                    name: None,
+                    age: None
+                },
+                Person {
+                    name: Some("Bo".into()),
+                    age: None
+                },
+                Person {
+                    name: Some("Bob".into()),
+                    age: None
+                },
+                Person {
+                    name: Some("Bob".into()),
+                    age: Some(42)
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streams_numbers_once_complete() {
+        let chunks = ["{\"a\": 1", "2.", "5}"];
+        let snapshots: Vec<Value> = stream::iter(chunks.map(|chunk| Ok(chunk.to_string())))
+            .partial_json::<Value>()
+            .map(|snapshot| snapshot.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(snapshots, vec![json!({}), json!({"a": 12.5})]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_fails_on_invalid_document() {
+        let chunks = ["{\"a\": 1", ", \"b\": }"];
+        let snapshots: Vec<_> = stream::iter(chunks.map(|chunk| Ok(chunk.to_string())))
+            .partial_json::<Value>()
+            .collect()
+            .await;
+
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].as_ref().unwrap(), &json!({}));
+        assert!(matches!(snapshots[1], Err(OpenAIError::SerdeJson(_))));
+    }
+}
@@ -1,9 +1,15 @@
-use super::{ChatStreamMessage, OpenAIChatModel, OpenAICompletionModel, OpenAIError};
-use futures::{stream::Skip, Stream, StreamExt};
+use super::{
+    telemetry::StreamTelemetry, ChatLogprobs, ChatModelResponse, ChatStreamMessage,
+    CompletionLogprobs, CompletionModelResponse, Deadline, OpenAIChatModel, OpenAICompletionModel,
+    OpenAIError, ToolCallDelta,
+};
+use futures::{ready, stream::Skip, Stream, StreamExt};
 use pin_project_lite::pin_project;
 use reqwest_eventsource::{Event, EventSource};
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
 use std::{
+    collections::{BTreeMap, VecDeque},
     marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
@@ -16,6 +22,9 @@ use std::{
 pub type ChatModelStream = OutputStream<OpenAIChatModel>;
 pub type CompletionModelStream = OutputStream<OpenAICompletionModel>;
 
+pub type ChatModelResponseStream = ResponseStream<OpenAIChatModel>;
+pub type CompletionModelResponseStream = ResponseStream<OpenAICompletionModel>;
+
 pub type CompletionModelStreamResponse = ModelStreamResponse<CompletionStreamChoice>;
 pub type ChatModelStreamResponse = ModelStreamResponse<ChatStreamChoice>;
 
@@ -24,13 +33,67 @@ pub type ChatModelStreamResponse = ModelStreamResponse<ChatStreamChoice>;
 //-------------------------------------------------------------------------------------------------
 
 pin_project! {
+    /// A stream of the text deltas generated by the model.
     pub struct OutputStream<M> {
+        #[pin]
+        inner: ResponseStream<M>,
+        received: String,
+    }
+}
+
+pin_project! {
+    /// A stream of the raw chunks sent by the model, including log probabilities and finish reasons.
+    pub struct ResponseStream<M> {
         model: PhantomData<M>,
+        // `None` when replaying a cached response.
+        #[pin]
+        event_src: Option<Skip<EventSource>>,
+        replayed: VecDeque<Value>,
+        telemetry: StreamTelemetry,
+        deadline: Deadline,
+    }
+}
+
+pin_project! {
+    /// A stream of content deltas and complete tool calls.
+    ///
+    /// Tool call fragments are accumulated per index. A call is emitted once the model moves on to
+    /// the next call, finishes its choice, or ends the stream, with its arguments parsed as JSON.
+    pub struct ChatEventStream<S = ChatModelResponseStream> {
         #[pin]
-        event_src: Skip<EventSource>,
+        inner: S,
+        pending: BTreeMap<usize, PendingToolCall>,
+        ready: VecDeque<Result<ChatStreamEvent, OpenAIError>>,
+        done: bool,
     }
 }
 
+/// An item of a `ChatEventStream`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatStreamEvent {
+    /// A text delta.
+    Content(String),
+
+    /// A tool call whose arguments have been fully generated.
+    ToolCall(ToolCall),
+}
+
+/// A complete tool call requested by the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub index: usize,
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Default)]
+struct PendingToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ModelStreamResponse<T> {
     pub id: String,
@@ -44,6 +107,7 @@ pub struct ModelStreamResponse<T> {
 pub struct ChatStreamChoice {
     pub index: u64,
     pub delta: ChatStreamMessage,
+    pub logprobs: Option<ChatLogprobs>,
     pub finish_reason: Option<String>,
 }
 
@@ -51,83 +115,489 @@ pub struct ChatStreamChoice {
 pub struct CompletionStreamChoice {
     pub index: u64,
     pub text: String,
-    pub logprobs: Option<u8>,
+    pub logprobs: Option<CompletionLogprobs>,
     pub finish_reason: Option<String>,
 }
 
 //-------------------------------------------------------------------------------------------------
-
 // Methods
 //-------------------------------------------------------------------------------------------------
 
 impl<M> OutputStream<M> {
+    pub fn new(event_src: EventSource) -> Self {
+        Self::from(ResponseStream::new(event_src))
+    }
+}
+
+impl<M> ResponseStream<M> {
     pub fn new(event_src: EventSource) -> Self {
         Self {
             model: PhantomData,
             // Skip the first event, which is always the "open" event
-            event_src: event_src.skip(1),
+            event_src: Some(event_src.skip(1)),
+            replayed: VecDeque::new(),
+            telemetry: StreamTelemetry::default(),
+            deadline: Deadline::default(),
+        }
+    }
+
+    /// Creates a stream that yields the given chunks without making a request.
+    fn replay(chunks: impl IntoIterator<Item = Value>) -> Self {
+        Self {
+            model: PhantomData,
+            event_src: None,
+            replayed: chunks.into_iter().collect(),
+            telemetry: StreamTelemetry::default(),
+            deadline: Deadline::default(),
+        }
+    }
+
+    pub(crate) fn with_telemetry(mut self, telemetry: StreamTelemetry) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// Ends the stream with an error, closing the connection, once the deadline fires.
+    pub(crate) fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+}
+
+impl ChatModelResponseStream {
+    /// Replays a complete response, such as a cached one, as a stream with one chunk per choice.
+    pub fn replay_response(response: ChatModelResponse) -> Self {
+        Self::replay(response.choices.into_iter().map(|choice| {
+            json!({
+                "id": response.id,
+                "object": "chat.completion.chunk",
+                "created": response.created,
+                "model": response.model,
+                "choices": [{
+                    "index": choice.index,
+                    "delta": {
+                        "role": choice.message.role,
+                        "content": String::from(choice.message.content),
+                    },
+                    "logprobs": choice.logprobs,
+                    "finish_reason": choice.finish_reason,
+                }],
+            })
+        }))
+    }
+}
+
+impl CompletionModelResponseStream {
+    /// Replays a complete response, such as a cached one, as a stream with one chunk per choice.
+    pub fn replay_response(response: CompletionModelResponse) -> Self {
+        Self::replay(response.choices.into_iter().map(|choice| {
+            json!({
+                "id": response.id,
+                "object": "text_completion",
+                "created": response.created,
+                "model": response.model,
+                "choices": [{
+                    "index": choice.index,
+                    "text": choice.text,
+                    "logprobs": choice.logprobs,
+                    "finish_reason": choice.finish_reason,
+                }],
+            })
+        }))
+    }
+}
+
+impl ChatModelStream {
+    /// Turns the stream into one that also yields complete tool calls.
+    pub fn with_tool_calls(self) -> ChatEventStream {
+        ChatEventStream::new(self.inner)
+    }
+}
+
+impl<S> ChatEventStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            pending: BTreeMap::new(),
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl PendingToolCall {
+    fn push(&mut self, delta: ToolCallDelta) {
+        if let Some(id) = delta.id {
+            self.id = id;
+        }
+
+        if let Some(function) = delta.function {
+            if let Some(name) = function.name {
+                self.name.push_str(&name);
+            }
+
+            if let Some(arguments) = function.arguments {
+                self.arguments.push_str(&arguments);
+            }
+        }
+    }
+
+    fn finish(self, index: usize) -> Result<ToolCall, OpenAIError> {
+        // Calls to functions without parameters may come with no arguments at all.
+        let arguments = if self.arguments.trim().is_empty() {
+            Ok(Value::Object(Default::default()))
+        } else {
+            serde_json::from_str(&self.arguments)
+        };
+
+        match arguments {
+            Ok(arguments) => Ok(ToolCall {
+                index,
+                id: self.id,
+                name: self.name,
+                arguments,
+            }),
+            Err(source) => Err(OpenAIError::InvalidToolArguments {
+                name: self.name,
+                arguments: self.arguments,
+                source,
+            }),
         }
     }
 }
 
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Keeps track of the text received so far, and reports it with cancellation and timeout errors.
+fn record_received(
+    received: &mut String,
+    mut poll: Poll<Option<Result<String, OpenAIError>>>,
+) -> Poll<Option<Result<String, OpenAIError>>> {
+    match &mut poll {
+        Poll::Ready(Some(Ok(text))) => received.push_str(text),
+        Poll::Ready(Some(Err(
+            OpenAIError::Cancelled { partial } | OpenAIError::Timeout { partial },
+        ))) => partial.clone_from(received),
+        _ => {}
+    }
+
+    poll
+}
+
+/// Moves the pending tool calls with an index below `index` to the ready queue.
+fn flush_tool_calls(
+    pending: &mut BTreeMap<usize, PendingToolCall>,
+    ready: &mut VecDeque<Result<ChatStreamEvent, OpenAIError>>,
+    index: usize,
+) {
+    let remaining = pending.split_off(&index);
+    for (index, call) in std::mem::replace(pending, remaining) {
+        ready.push_back(call.finish(index).map(ChatStreamEvent::ToolCall));
+    }
+}
+
+fn poll_response<T>(
+    event_src: Pin<&mut Option<Skip<EventSource>>>,
+    replayed: &mut VecDeque<Value>,
+    deadline: &mut Deadline,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Result<T, OpenAIError>>>
+where
+    T: DeserializeOwned,
+{
+    let mut event_src = match event_src.as_pin_mut() {
+        Some(event_src) => event_src,
+        None => {
+            return Poll::Ready(
+                replayed
+                    .pop_front()
+                    .map(|chunk| serde_json::from_value(chunk).map_err(OpenAIError::SerdeJson)),
+            )
+        }
+    };
+
+    if let Poll::Ready(err) = deadline.poll_expired(cx) {
+        event_src.as_mut().get_mut().get_mut().close();
+        return Poll::Ready(Some(Err(err)));
+    }
+
+    match event_src.poll_next(cx) {
+        Poll::Ready(Some(Ok(Event::Message(event)))) => {
+            #[cfg(feature = "log")]
+            log::debug!("eventsource message: {event:#?}");
+
+            if event.data == "[DONE]" {
+                return Poll::Ready(None);
+            }
+
+            Poll::Ready(Some(
+                serde_json::from_str(&event.data).map_err(OpenAIError::SerdeJson),
+            ))
+        }
+        Poll::Ready(Some(Ok(Event::Open))) => unreachable!(),
+        Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
+        Poll::Ready(None) => Poll::Ready(None),
+        Poll::Pending => Poll::Pending,
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Trait Implementations
 //-------------------------------------------------------------------------------------------------
 
-impl Stream for ChatModelStream {
-    type Item = Result<String, OpenAIError>;
+impl<M> From<ResponseStream<M>> for OutputStream<M> {
+    fn from(inner: ResponseStream<M>) -> Self {
+        Self {
+            inner,
+            received: String::new(),
+        }
+    }
+}
+
+impl Stream for ChatModelResponseStream {
+    type Item = Result<ChatModelStreamResponse, OpenAIError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
-        match this.event_src.poll_next(cx) {
-            Poll::Ready(Some(Ok(Event::Message(event)))) => {
-                #[cfg(feature = "log")]
-                log::debug!("eventsource message: {event:#?}");
+        let poll = poll_response(this.event_src, this.replayed, this.deadline, cx);
+        this.telemetry.observe(
+            &poll,
+            |response: &ChatModelStreamResponse| {
+                response.choices.iter().any(|choice| {
+                    choice
+                        .delta
+                        .content
+                        .as_ref()
+                        .map_or(false, |content| !content.is_empty())
+                })
+            },
+            |response| response.choices.first()?.finish_reason.as_deref(),
+        );
 
-                if event.data == "[DONE]" {
-                    return Poll::Ready(None);
+        poll
+    }
+}
+
+impl Stream for CompletionModelResponseStream {
+    type Item = Result<CompletionModelStreamResponse, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let poll = poll_response(this.event_src, this.replayed, this.deadline, cx);
+        this.telemetry.observe(
+            &poll,
+            |response: &CompletionModelStreamResponse| {
+                response
+                    .choices
+                    .iter()
+                    .any(|choice| !choice.text.is_empty())
+            },
+            |response| response.choices.first()?.finish_reason.as_deref(),
+        );
+
+        poll
+    }
+}
+
+impl<S> Stream for ChatEventStream<S>
+where
+    S: Stream<Item = Result<ChatModelStreamResponse, OpenAIError>>,
+{
+    type Item = Result<ChatStreamEvent, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if let Some(event) = this.ready.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+
+            if *this.done {
+                return Poll::Ready(None);
+            }
+
+            let response = match ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(response)) => response,
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    *this.done = true;
+                    flush_tool_calls(this.pending, this.ready, usize::MAX);
+                    continue;
                 }
+            };
+
+            let choice = match response.choices.into_iter().next() {
+                Some(choice) => choice,
+                None => continue,
+            };
 
-                let response: ChatModelStreamResponse =
-                    serde_json::from_str(&event.data).map_err(OpenAIError::SerdeJson)?;
+            if let Some(content) = choice.delta.content.filter(|content| !content.is_empty()) {
+                this.ready.push_back(Ok(ChatStreamEvent::Content(content)));
+            }
+
+            for delta in choice.delta.tool_calls.unwrap_or_default() {
+                // Calls are generated one after the other, so a new index means the previous ones
+                // are complete.
+                flush_tool_calls(this.pending, this.ready, delta.index);
+                this.pending.entry(delta.index).or_default().push(delta);
+            }
 
-                Poll::Ready(Some(Ok(response.choices[0]
-                    .delta
-                    .content
-                    .clone()
-                    .unwrap_or_default())))
+            if choice.finish_reason.is_some() {
+                flush_tool_calls(this.pending, this.ready, usize::MAX);
             }
-            Poll::Ready(Some(Ok(Event::Open))) => unreachable!(),
-            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
         }
     }
 }
 
+impl Stream for ChatModelStream {
+    type Item = Result<String, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let poll = this.inner.poll_next(cx).map_ok(|response| {
+            response
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.delta.content)
+                .unwrap_or_default()
+        });
+
+        record_received(this.received, poll)
+    }
+}
+
 impl Stream for CompletionModelStream {
     type Item = Result<String, OpenAIError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
-        match this.event_src.poll_next(cx) {
-            Poll::Ready(Some(Ok(Event::Message(event)))) => {
-                #[cfg(feature = "log")]
-                log::debug!("eventsource message: {event:#?}");
+        let poll = this.inner.poll_next(cx).map_ok(|response| {
+            response
+                .choices
+                .into_iter()
+                .next()
+                .map(|choice| choice.text)
+                .unwrap_or_default()
+        });
 
-                if event.data == "[DONE]" {
-                    return Poll::Ready(None);
-                }
+        record_received(this.received, poll)
+    }
+}
 
-                let response: CompletionModelStreamResponse =
-                    serde_json::from_str(&event.data).map_err(OpenAIError::SerdeJson)?;
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
 
-                Poll::Ready(Some(Ok(response.choices[0].text.clone())))
-            }
-            Poll::Ready(Some(Ok(Event::Open))) => unreachable!(),
-            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use serde_json::json;
+
+    fn chunk(
+        delta: Value,
+        finish_reason: Option<&str>,
+    ) -> Result<ChatModelStreamResponse, OpenAIError> {
+        Ok(serde_json::from_value(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion.chunk",
+            "created": 1694268190,
+            "model": "gpt-3.5-turbo-0613",
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }]
+        }))
+        .unwrap())
+    }
+
+    fn tool_delta(index: usize, name: Option<&str>, arguments: &str) -> Value {
+        let mut delta = json!({
+            "index": index,
+            "function": { "arguments": arguments }
+        });
+
+        if let Some(name) = name {
+            delta["id"] = json!(format!("call_{index}"));
+            delta["type"] = json!("function");
+            delta["function"]["name"] = json!(name);
         }
+
+        json!({ "tool_calls": [delta] })
+    }
+
+    #[tokio::test]
+    async fn test_aggregates_tool_call_deltas() {
+        let chunks = vec![
+            chunk(json!({ "role": "assistant", "content": "Checking" }), None),
+            chunk(tool_delta(0, Some("get_weather"), ""), None),
+            chunk(tool_delta(0, None, "{\"city\": "), None),
+            chunk(tool_delta(0, None, "\"Paris\"}"), None),
+            chunk(tool_delta(1, Some("get_time"), "{}"), None),
+            chunk(json!({}), Some("tool_calls")),
+        ];
+
+        let events: Vec<_> = ChatEventStream::new(stream::iter(chunks))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            events,
+            vec![
+                ChatStreamEvent::Content("Checking".into()),
+                ChatStreamEvent::ToolCall(ToolCall {
+                    index: 0,
+                    id: "call_0".into(),
+                    name: "get_weather".into(),
+                    arguments: json!({ "city": "Paris" }),
+                }),
+                ChatStreamEvent::ToolCall(ToolCall {
+                    index: 1,
+                    id: "call_1".into(),
+                    name: "get_time".into(),
+                    arguments: json!({}),
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replays_cached_response_as_stream() {
+        let response: ChatModelResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1694268190,
+            "model": "gpt-3.5-turbo-0613",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hello there!" },
+                "logprobs": null,
+                "finish_reason": "stop"
+            }],
+            "usage": null
+        }))
+        .unwrap();
+
+        let stream = ChatModelStream::from(ChatModelResponseStream::replay_response(response));
+        let output: Vec<_> = stream.map(Result::unwrap).collect().await;
+
+        assert_eq!(output, vec!["Hello there!"]);
+    }
+
+    #[tokio::test]
+    async fn test_fails_on_invalid_tool_arguments() {
+        let chunks = vec![chunk(
+            tool_delta(0, Some("get_weather"), "{\"city\": "),
+            None,
+        )];
+
+        let events: Vec<_> = ChatEventStream::new(stream::iter(chunks)).collect().await;
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            Err(OpenAIError::InvalidToolArguments { name, .. }) if name == "get_weather"
+        ));
     }
 }
@@ -0,0 +1,579 @@
+//! Combinators for streams of text deltas such as `ChatModelStream` and `CompletionModelStream`.
+
+use super::{OpenAIError, PartialJson};
+use futures::{ready, Stream};
+use pin_project_lite::pin_project;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use tokio::time::{Instant, Sleep};
+
+//-------------------------------------------------------------------------------------------------
+// Traits
+//-------------------------------------------------------------------------------------------------
+
+/// Extension methods for streams of text deltas.
+pub trait TextStreamExt: Stream<Item = Result<String, OpenAIError>> + Sized {
+    /// Re-chunks the stream into words, each carrying its leading whitespace.
+    fn words(self) -> Rechunk<Self> {
+        Rechunk::new(self, Boundary::Word)
+    }
+
+    /// Re-chunks the stream into sentences, each carrying its leading whitespace.
+    ///
+    /// A sentence ends at a run of `.`, `!` or `?` followed by whitespace, so abbreviations like
+    /// "e.g. this" are split too.
+    fn sentences(self) -> Rechunk<Self> {
+        Rechunk::new(self, Boundary::Sentence)
+    }
+
+    /// Re-chunks the stream into lines, each including its trailing newline.
+    fn lines(self) -> Rechunk<Self> {
+        Rechunk::new(self, Boundary::Line)
+    }
+
+    /// Ends the stream as soon as `pattern` appears, even if it is split across chunks.
+    ///
+    /// The pattern and everything after it are dropped.
+    fn stop_at(self, pattern: impl Into<String>) -> StopAt<Self> {
+        StopAt::new(self, pattern.into())
+    }
+
+    /// Fails with `OpenAIError::IdleTimeout` if no chunk arrives within `duration`.
+    fn idle_timeout(self, duration: Duration) -> IdleTimeout<Self> {
+        IdleTimeout::new(self, duration)
+    }
+
+    /// Collects the stream into a `String`, passing every chunk to `callback` as it arrives.
+    fn collect_tee<F>(self, callback: F) -> CollectTee<Self, F>
+    where
+        F: FnMut(&str),
+    {
+        CollectTee::new(self, callback)
+    }
+
+    /// Parses the stream as a JSON document, yielding a snapshot every time it grows.
+    ///
+    /// See `parse_partial_json` for how unfinished values are handled.
+    fn partial_json<T>(self) -> PartialJson<Self, T>
+    where
+        T: DeserializeOwned,
+    {
+        PartialJson::new(self)
+    }
+
+    /// Shares the stream between multiple subscribers.
+    ///
+    /// Every subscriber sees every chunk produced after it subscribed. The source is driven by
+    /// whichever subscriber is polled, so subscribers should be created before any of them is
+    /// polled.
+    fn broadcast(self) -> Broadcast<Self> {
+        Broadcast::new(self)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// The unit a `Rechunk` stream splits text into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    Word,
+    Sentence,
+    Line,
+}
+
+pin_project! {
+    /// A stream that re-chunks text deltas along a `Boundary`.
+    pub struct Rechunk<S> {
+        #[pin]
+        inner: S,
+        boundary: Boundary,
+        buffer: String,
+        done: bool,
+    }
+}
+
+pin_project! {
+    /// A stream that ends when a stop pattern appears.
+    pub struct StopAt<S> {
+        #[pin]
+        inner: S,
+        pattern: String,
+        buffer: String,
+        done: bool,
+    }
+}
+
+pin_project! {
+    /// A stream that fails if the inner stream stays idle for too long.
+    pub struct IdleTimeout<S> {
+        #[pin]
+        inner: S,
+        sleep: Option<Pin<Box<Sleep>>>,
+        duration: Duration,
+        done: bool,
+    }
+}
+
+pin_project! {
+    /// A future that collects a stream of text deltas while teeing them to a callback.
+    pub struct CollectTee<S, F> {
+        #[pin]
+        inner: S,
+        callback: F,
+        output: String,
+    }
+}
+
+/// A stream shared between multiple subscribers.
+pub struct Broadcast<S> {
+    shared: Arc<Mutex<Shared<S>>>,
+}
+
+/// One subscriber of a `Broadcast` stream.
+///
+/// Errors from the source are delivered to every subscriber as `OpenAIError::Shared`.
+pub struct Subscriber<S> {
+    id: usize,
+    shared: Arc<Mutex<Shared<S>>>,
+}
+
+struct Shared<S> {
+    source: Pin<Box<S>>,
+    queues: Vec<Option<SubscriberQueue>>,
+    done: bool,
+}
+
+#[derive(Default)]
+struct SubscriberQueue {
+    items: VecDeque<Result<String, OpenAIError>>,
+    waker: Option<Waker>,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl<S> Rechunk<S> {
+    fn new(inner: S, boundary: Boundary) -> Self {
+        Self {
+            inner,
+            boundary,
+            buffer: String::new(),
+            done: false,
+        }
+    }
+}
+
+impl Boundary {
+    /// Returns the byte index right after the first complete unit in `text`.
+    fn split(&self, text: &str) -> Option<usize> {
+        match self {
+            Boundary::Word => {
+                let mut seen_word = false;
+                text.char_indices().find_map(|(i, c)| {
+                    if !c.is_whitespace() {
+                        seen_word = true;
+                        None
+                    } else {
+                        seen_word.then_some(i)
+                    }
+                })
+            }
+            Boundary::Sentence => {
+                let mut chars = text.char_indices().peekable();
+                while let Some((i, c)) = chars.next() {
+                    let next = chars.peek().map(|(_, next)| *next);
+                    if matches!(c, '.' | '!' | '?') && next.map_or(false, char::is_whitespace) {
+                        return Some(i + c.len_utf8());
+                    }
+                }
+                None
+            }
+            Boundary::Line => text.find('\n').map(|i| i + 1),
+        }
+    }
+}
+
+impl<S> StopAt<S> {
+    fn new(inner: S, pattern: String) -> Self {
+        Self {
+            inner,
+            pattern,
+            buffer: String::new(),
+            done: false,
+        }
+    }
+}
+
+/// Returns the length of the longest suffix of `text` that is a proper prefix of `pattern`.
+fn partial_match_len(text: &str, pattern: &str) -> usize {
+    (1..pattern.len().min(text.len() + 1))
+        .rev()
+        .find(|&len| pattern.is_char_boundary(len) && text.ends_with(&pattern[..len]))
+        .unwrap_or(0)
+}
+
+impl<S> IdleTimeout<S> {
+    fn new(inner: S, duration: Duration) -> Self {
+        Self {
+            inner,
+            sleep: None,
+            duration,
+            done: false,
+        }
+    }
+}
+
+impl<S, F> CollectTee<S, F> {
+    fn new(inner: S, callback: F) -> Self {
+        Self {
+            inner,
+            callback,
+            output: String::new(),
+        }
+    }
+}
+
+impl<S> Broadcast<S> {
+    fn new(source: S) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                source: Box::pin(source),
+                queues: vec![],
+                done: false,
+            })),
+        }
+    }
+
+    /// Creates a new subscriber.
+    pub fn subscribe(&self) -> Subscriber<S> {
+        let mut shared = self.shared.lock().unwrap();
+        shared.queues.push(Some(SubscriberQueue::default()));
+
+        Subscriber {
+            id: shared.queues.len() - 1,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<S> Shared<S> {
+    fn wake_others(&mut self, id: usize) {
+        for (_, queue) in self
+            .queues
+            .iter_mut()
+            .enumerate()
+            .filter(|(other, _)| *other != id)
+        {
+            if let Some(waker) = queue.as_mut().and_then(|queue| queue.waker.take()) {
+                waker.wake();
+            }
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl<S> TextStreamExt for S where S: Stream<Item = Result<String, OpenAIError>> {}
+
+impl<S> Stream for Rechunk<S>
+where
+    S: Stream<Item = Result<String, OpenAIError>>,
+{
+    type Item = Result<String, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if let Some(end) = this.boundary.split(this.buffer) {
+                return Poll::Ready(Some(Ok(this.buffer.drain(..end).collect())));
+            }
+
+            if *this.done {
+                if this.buffer.is_empty() {
+                    return Poll::Ready(None);
+                }
+
+                return Poll::Ready(Some(Ok(std::mem::take(this.buffer))));
+            }
+
+            match ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => this.buffer.push_str(&chunk),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => *this.done = true,
+            }
+        }
+    }
+}
+
+impl<S> Stream for StopAt<S>
+where
+    S: Stream<Item = Result<String, OpenAIError>>,
+{
+    type Item = Result<String, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if *this.done {
+                return Poll::Ready(None);
+            }
+
+            let chunk = match ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    *this.done = true;
+                    if this.buffer.is_empty() {
+                        return Poll::Ready(None);
+                    }
+
+                    return Poll::Ready(Some(Ok(std::mem::take(this.buffer))));
+                }
+            };
+
+            this.buffer.push_str(&chunk);
+            if let Some(start) = this.buffer.find(this.pattern.as_str()) {
+                *this.done = true;
+                this.buffer.truncate(start);
+                if this.buffer.is_empty() {
+                    return Poll::Ready(None);
+                }
+
+                return Poll::Ready(Some(Ok(std::mem::take(this.buffer))));
+            }
+
+            // Hold back the tail that could still turn into the pattern.
+            let held = partial_match_len(this.buffer, this.pattern);
+            let ready = this.buffer.len() - held;
+            if ready > 0 {
+                return Poll::Ready(Some(Ok(this.buffer.drain(..ready).collect())));
+            }
+        }
+    }
+}
+
+impl<S> Stream for IdleTimeout<S>
+where
+    S: Stream<Item = Result<String, OpenAIError>>,
+{
+    type Item = Result<String, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        // The timer starts on the first poll, so the stream can be created outside a runtime.
+        let deadline = Instant::now() + *this.duration;
+        let sleep = this
+            .sleep
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+
+        if let Poll::Ready(item) = this.inner.poll_next(cx) {
+            sleep.as_mut().reset(deadline);
+            *this.done = item.is_none();
+            return Poll::Ready(item);
+        }
+
+        ready!(sleep.as_mut().poll(cx));
+        *this.done = true;
+        Poll::Ready(Some(Err(OpenAIError::IdleTimeout(*this.duration))))
+    }
+}
+
+impl<S, F> Future for CollectTee<S, F>
+where
+    S: Stream<Item = Result<String, OpenAIError>>,
+    F: FnMut(&str),
+{
+    type Output = Result<String, OpenAIError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match ready!(this.inner.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    (this.callback)(&chunk);
+                    this.output.push_str(&chunk);
+                }
+                Some(Err(err)) => return Poll::Ready(Err(err)),
+                None => return Poll::Ready(Ok(std::mem::take(this.output))),
+            }
+        }
+    }
+}
+
+impl<S> Stream for Subscriber<S>
+where
+    S: Stream<Item = Result<String, OpenAIError>>,
+{
+    type Item = Result<String, OpenAIError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let id = self.id;
+        let mut shared = self.shared.lock().unwrap();
+
+        loop {
+            if let Some(item) = shared.queues[id]
+                .as_mut()
+                .and_then(|queue| queue.items.pop_front())
+            {
+                return Poll::Ready(Some(item));
+            }
+
+            if shared.done {
+                return Poll::Ready(None);
+            }
+
+            match shared.source.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let item = item.map_err(Arc::new);
+                    for queue in shared.queues.iter_mut().flatten() {
+                        queue
+                            .items
+                            .push_back(item.clone().map_err(OpenAIError::Shared));
+                    }
+                }
+                Poll::Ready(None) => shared.done = true,
+                Poll::Pending => {
+                    if let Some(queue) = shared.queues[id].as_mut() {
+                        queue.waker = Some(cx.waker().clone());
+                    }
+
+                    return Poll::Pending;
+                }
+            }
+
+            // The source only remembers the waker of the last subscriber that polled it.
+            shared.wake_others(id);
+        }
+    }
+}
+
+impl<S> Drop for Subscriber<S> {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.queues[self.id] = None;
+            // The source may be holding this subscriber's waker, so let another one take over.
+            shared.wake_others(self.id);
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{stream, StreamExt};
+
+    fn chunks(chunks: &[&str]) -> impl Stream<Item = Result<String, OpenAIError>> {
+        stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok(chunk.to_string()))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    async fn collect(stream: impl Stream<Item = Result<String, OpenAIError>>) -> Vec<String> {
+        stream.map(|chunk| chunk.unwrap()).collect().await
+    }
+
+    #[tokio::test]
+    async fn test_rechunks_into_words_sentences_and_lines() {
+        let words = collect(chunks(&["Hel", "lo wo", "rld  ", "again"]).words()).await;
+        assert_eq!(words, vec!["Hello", " world", "  again"]);
+
+        let sentences =
+            collect(chunks(&["Hi there", ". How are", " you?", " Fine!"]).sentences()).await;
+        assert_eq!(sentences, vec!["Hi there.", " How are you?", " Fine!"]);
+
+        let lines = collect(chunks(&["one\ntw", "o\n", "three"]).lines()).await;
+        assert_eq!(lines, vec!["one\n", "two\n", "three"]);
+    }
+
+    #[tokio::test]
+    async fn test_stop_at_pattern_split_across_chunks() {
+        let output = collect(chunks(&["Answer: 4", "2\nEN", "D more text"]).stop_at("END")).await;
+        assert_eq!(output.concat(), "Answer: 42\n");
+
+        let output = collect(chunks(&["no E", "N", "d here"]).stop_at("END")).await;
+        assert_eq!(output.concat(), "no ENd here");
+    }
+
+    #[tokio::test]
+    async fn test_stop_at_pattern_at_start_of_chunk() {
+        let output = collect(chunks(&["Answer", "END more text"]).stop_at("END")).await;
+        assert_eq!(output, vec!["Answer"]);
+
+        let output = collect(chunks(&["END more text"]).stop_at("END")).await;
+        assert!(output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let stalled = chunks(&["first"]).chain(stream::pending());
+        let mut stream = Box::pin(stalled.idle_timeout(Duration::from_millis(10)));
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "first");
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(OpenAIError::IdleTimeout(_)))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn test_idle_timeout_starts_on_first_poll() {
+        let stream = chunks(&["first", "second"]).idle_timeout(Duration::from_millis(10));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let output = runtime.block_on(async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            collect(stream).await
+        });
+        assert_eq!(output, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_collect_tee() {
+        let mut seen = vec![];
+        let output = chunks(&["a", "b", "c"])
+            .collect_tee(|chunk| seen.push(chunk.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(output, "abc");
+        assert_eq!(seen, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_multiple_subscribers() {
+        let broadcast = chunks(&["a", "b", "c"]).broadcast();
+        let first = broadcast.subscribe();
+        let second = broadcast.subscribe();
+
+        let (first, second) = futures::join!(collect(first), collect(second));
+        assert_eq!(first, vec!["a", "b", "c"]);
+        assert_eq!(second, vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,211 @@
+//! Tracing instrumentation for model calls.
+//!
+//! Everything in here compiles down to nothing unless the `tracing` feature is enabled. Request and
+//! response bodies are only recorded when the `tracing-bodies` feature is enabled as well.
+
+use super::{OpenAIError, Usage};
+use serde::Serialize;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+use std::{
+    fmt::{Debug, Display},
+    future::Future,
+    task::Poll,
+};
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, Instrument, Span};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// The span covering a single call to a model endpoint.
+#[derive(Debug, Clone)]
+pub(crate) struct CallSpan {
+    #[cfg(feature = "tracing")]
+    span: Span,
+
+    #[cfg(feature = "tracing")]
+    started: Instant,
+}
+
+/// Per-chunk instrumentation for streamed responses.
+#[derive(Debug, Default)]
+pub(crate) struct StreamTelemetry {
+    #[cfg(feature = "tracing")]
+    call: Option<CallSpan>,
+
+    #[cfg(feature = "tracing")]
+    chunks: u64,
+
+    /// Whether a chunk with text has been seen yet.
+    #[cfg(feature = "tracing")]
+    first_token: bool,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl CallSpan {
+    /// Opens a span for a call to the given model and endpoint.
+    #[allow(unused_variables)]
+    pub(crate) fn new(model: &dyn Display, endpoint: &str, stream: bool) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "model.call",
+                model = %model,
+                endpoint,
+                stream,
+                latency_ms = Empty,
+                time_to_first_token_ms = Empty,
+                chunks = Empty,
+                prompt_tokens = Empty,
+                completion_tokens = Empty,
+                total_tokens = Empty,
+                finish_reason = Empty,
+                error = Empty,
+                request_body = Empty,
+                response_body = Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            started: Instant::now(),
+        }
+    }
+
+    /// Runs the future inside the span.
+    pub(crate) fn instrument<F>(&self, future: F) -> impl Future<Output = F::Output>
+    where
+        F: Future,
+    {
+        #[cfg(feature = "tracing")]
+        return future.instrument(self.span.clone());
+
+        #[cfg(not(feature = "tracing"))]
+        future
+    }
+
+    /// Records the serialized request body.
+    #[allow(unused_variables)]
+    pub(crate) fn record_request(&self, body: &impl Serialize) {
+        #[cfg(feature = "tracing-bodies")]
+        if let Ok(body) = serde_json::to_string(body) {
+            self.span.record("request_body", body.as_str());
+        }
+    }
+
+    /// Records the outcome of the call along with its latency.
+    #[allow(unused_variables)]
+    pub(crate) fn record_result<T>(
+        &self,
+        result: &Result<T, OpenAIError>,
+        usage: impl FnOnce(&T) -> Option<&Usage>,
+        finish_reason: impl FnOnce(&T) -> Option<&str>,
+    ) where
+        T: Debug,
+    {
+        #[cfg(feature = "tracing")]
+        {
+            self.record_latency();
+            match result {
+                Ok(response) => {
+                    self.record_usage(usage(response));
+                    self.record_finish_reason(finish_reason(response));
+
+                    #[cfg(feature = "tracing-bodies")]
+                    self.span
+                        .record("response_body", format!("{response:?}").as_str());
+                }
+                Err(err) => self.record_error(err),
+            }
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record_latency(&self) {
+        self.span
+            .record("latency_ms", self.started.elapsed().as_millis() as u64);
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record_usage(&self, usage: Option<&Usage>) {
+        if let Some(usage) = usage {
+            self.span.record("prompt_tokens", usage.prompt_tokens);
+            self.span
+                .record("completion_tokens", usage.completion_tokens);
+            self.span.record("total_tokens", usage.total_tokens);
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record_finish_reason(&self, finish_reason: Option<&str>) {
+        if let Some(finish_reason) = finish_reason {
+            self.span.record("finish_reason", finish_reason);
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record_error(&self, err: &OpenAIError) {
+        self.span.record("error", tracing::field::display(err));
+        tracing::warn!(parent: &self.span, error = %err, "model call failed");
+    }
+}
+
+impl StreamTelemetry {
+    /// Creates stream instrumentation that reports to the given call span.
+    #[allow(unused_variables)]
+    pub(crate) fn new(call: CallSpan) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            call: Some(call),
+            #[cfg(feature = "tracing")]
+            chunks: 0,
+            #[cfg(feature = "tracing")]
+            first_token: false,
+        }
+    }
+
+    /// Records a polled chunk as an event on the call span.
+    ///
+    /// The time to first token is taken at the first chunk for which `has_text` is true, since the
+    /// first chunks may only carry the role.
+    #[allow(unused_variables)]
+    pub(crate) fn observe<T>(
+        &mut self,
+        poll: &Poll<Option<Result<T, OpenAIError>>>,
+        has_text: impl FnOnce(&T) -> bool,
+        finish_reason: impl FnOnce(&T) -> Option<&str>,
+    ) where
+        T: Debug,
+    {
+        #[cfg(feature = "tracing")]
+        if let Some(call) = &self.call {
+            let elapsed_ms = call.started.elapsed().as_millis() as u64;
+            match poll {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.chunks += 1;
+                    if !self.first_token && has_text(chunk) {
+                        self.first_token = true;
+                        call.span.record("time_to_first_token_ms", elapsed_ms);
+                    }
+
+                    call.record_finish_reason(finish_reason(chunk));
+
+                    #[cfg(feature = "tracing-bodies")]
+                    tracing::trace!(parent: &call.span, chunk = self.chunks, elapsed_ms, body = ?chunk, "stream chunk");
+
+                    #[cfg(not(feature = "tracing-bodies"))]
+                    tracing::trace!(parent: &call.span, chunk = self.chunks, elapsed_ms, "stream chunk");
+                }
+                Poll::Ready(Some(Err(err))) => call.record_error(err),
+                Poll::Ready(None) => {
+                    call.record_latency();
+                    call.span.record("chunks", self.chunks);
+                    tracing::debug!(parent: &call.span, chunks = self.chunks, elapsed_ms, "stream finished");
+                }
+                Poll::Pending => {}
+            }
+        }
+    }
+}
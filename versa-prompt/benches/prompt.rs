@@ -1,5 +1,5 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use std::vec;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use std::{collections::HashMap, vec};
 use versa_prompt::{map, FinalizablePrompt, Prompt, PromptList, Role, Tag};
 
 fn prompt_create(c: &mut Criterion) {
@@ -50,5 +50,76 @@ fn prompt_format(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, prompt_create, prompt_add_message, prompt_format);
+fn prompt_format_large(c: &mut Criterion) {
+    let paragraph = "The quick brown fox jumps over the lazy dog while {{animal}} watches. ";
+    let source = paragraph.repeat(2_000);
+
+    c.bench_function("prompt_create_large", |b| {
+        b.iter(|| Prompt::new(black_box(source.as_str())))
+    });
+
+    c.bench_function("prompt_format_large", |b| {
+        b.iter_batched(
+            || Prompt::new(source.as_str()),
+            |mut prompt| {
+                prompt
+                    .format(black_box(map!("animal" => "a curious cat")))
+                    .unwrap();
+                prompt
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn prompt_format_many_vars(c: &mut Criterion) {
+    let names: Vec<_> = (0..500).map(|i| format!("var_{i}")).collect();
+    let values: Vec<_> = (0..500).map(|i| format!("value {i}")).collect();
+    let source: String = names
+        .iter()
+        .map(|name| format!("{name} is {{{{{name}}}}}. "))
+        .collect();
+    let map: HashMap<&str, &str> = names
+        .iter()
+        .map(String::as_str)
+        .zip(values.iter().map(String::as_str))
+        .collect();
+
+    c.bench_function("prompt_format_many_vars", |b| {
+        b.iter_batched(
+            || Prompt::new(source.as_str()),
+            |mut prompt| {
+                prompt.format(black_box(map.clone())).unwrap();
+                prompt.finalize().unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("prompt_list_format_many_vars", |b| {
+        b.iter_batched(
+            || {
+                let mut prompt = PromptList::new(source.as_str(), vec![Tag::Role(Role::System)]);
+                for _ in 0..10 {
+                    prompt.add_message(source.as_str(), vec![Tag::Role(Role::User)]);
+                }
+                prompt
+            },
+            |mut prompt| {
+                prompt.format(black_box(map.clone())).unwrap();
+                prompt.finalize().unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(
+    benches,
+    prompt_create,
+    prompt_add_message,
+    prompt_format,
+    prompt_format_large,
+    prompt_format_many_vars
+);
 criterion_main!(benches);
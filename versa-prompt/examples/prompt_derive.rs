@@ -0,0 +1,48 @@
+use anyhow::Result;
+use serde::Serialize;
+use versa_prompt::PromptTemplate;
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+#[derive(Serialize, PromptTemplate)]
+#[prompt(
+    system = "Classify the text into neutral, negative or positive.",
+    user = "{{text}}"
+)]
+struct Classify<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize, PromptTemplate)]
+#[prompt(path = "examples/prompts/review.txt")]
+struct Review {
+    language: String,
+    focus: Option<String>,
+    code: String,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Main
+//-------------------------------------------------------------------------------------------------
+
+fn main() -> Result<()> {
+    let prompt = Classify {
+        text: "I was not happy with the service.",
+    }
+    .to_prompt()?;
+
+    println!("Prompt = {prompt:?}");
+
+    let prompt = Review {
+        language: "Rust".into(),
+        focus: Some("error handling".into()),
+        code: "let file = File::open(path).unwrap();".into(),
+    }
+    .to_prompt()?;
+
+    println!("Prompt = {}", String::from(prompt));
+
+    Ok(())
+}
@@ -1,10 +1,34 @@
 use anyhow::{Ok, Result};
+use versa_prompt::{map, prompt, select, ChatFormat, FinalizablePrompt};
 
 //-------------------------------------------------------------------------------------------------
 // Main
 //-------------------------------------------------------------------------------------------------
 
-// TODO(nyprothegeek): Implement.
 fn main() -> Result<()> {
+    let prompt = select! {
+        "gpt-4*" => prompt!(
+            system: "Classify the text into neutral, negative or positive.",
+            user: "{{text}}"
+        ),
+        "llama-2-*" => prompt!(
+            system: "Classify the text into neutral, negative or positive. Answer with one word.",
+            user: "Text: {{text}}"
+        ),
+        _ => prompt!(user: "Classify the text into neutral, negative or positive: {{text}}"),
+    };
+
+    for model in ["gpt-4-turbo", "llama-2-13b-chat", "mistral-7b"] {
+        let resolved = prompt
+            .select(model)?
+            .clone()
+            .resolve(map!("text" => "I was not happy with the service."))?;
+
+        println!(
+            "{model}:\n{}\n",
+            resolved.render_with(&ChatFormat::for_model(model))
+        );
+    }
+
     Ok(())
 }
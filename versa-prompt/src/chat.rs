@@ -0,0 +1,300 @@
+use crate::{Content, ResolvedPromptList, Role, Tag, Tags};
+use serde::{Deserialize, Serialize};
+
+//-------------------------------------------------------------------------------------------------
+// Traits
+//-------------------------------------------------------------------------------------------------
+
+/// Renders role-tagged messages into a single string for models that take plain text.
+pub trait ChatTemplate {
+    /// Renders the prompt, ending with the cue for the assistant to answer.
+    fn render(&self, prompt: &ResolvedPromptList) -> String;
+}
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// The chat formats that common models are trained on.
+///
+/// Messages without a role are treated as user messages, except by `Plain` which writes them as
+/// they are. Images are left out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatFormat {
+    /// `<|im_start|>role\ncontent<|im_end|>`, used by OpenHermes, Qwen and others.
+    ChatMl,
+
+    /// `<s>[INST] <<SYS>>\nsystem\n<</SYS>>\n\nuser [/INST] assistant </s>`, used by Llama 2 chat.
+    Llama2,
+
+    /// `### Instruction:` and `### Response:` sections, used by Alpaca.
+    Alpaca,
+
+    /// `USER:` and `ASSISTANT:` turns, used by Vicuna.
+    Vicuna,
+
+    /// `Role: content` lines.
+    #[default]
+    Plain,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl ChatFormat {
+    /// Picks the format a model is trained on from its id, falling back to `Plain`.
+    pub fn for_model(id: &str) -> Self {
+        let id = id.to_lowercase();
+        let has = |pattern: &str| id.contains(pattern);
+        if has("llama-2") || has("llama2") {
+            ChatFormat::Llama2
+        } else if has("vicuna") {
+            ChatFormat::Vicuna
+        } else if has("alpaca") {
+            ChatFormat::Alpaca
+        } else if has("chatml") || has("hermes") || has("qwen") || has("dolphin") {
+            ChatFormat::ChatMl
+        } else {
+            ChatFormat::Plain
+        }
+    }
+}
+
+impl ResolvedPromptList {
+    /// Renders the messages into a single string with the given chat template.
+    pub fn render_with(&self, template: &impl ChatTemplate) -> String {
+        template.render(self)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+fn role(tags: &Tags) -> Option<&Role> {
+    tags.iter().find_map(|tag| match tag {
+        Tag::Role(role) => Some(role),
+        Tag::Pattern(_) => None,
+    })
+}
+
+fn text(content: &Content) -> String {
+    content.texts().collect::<Vec<_>>().join("\n")
+}
+
+fn render_chatml(prompt: &ResolvedPromptList) -> String {
+    let mut output = String::new();
+    for (content, tags) in prompt.iter() {
+        let role = match role(tags) {
+            Some(Role::System) => "system",
+            Some(Role::Assistant) => "assistant",
+            Some(Role::User) | None => "user",
+        };
+
+        output.push_str(&format!(
+            "<|im_start|>{role}\n{}<|im_end|>\n",
+            text(content)
+        ));
+    }
+
+    output.push_str("<|im_start|>assistant\n");
+    output
+}
+
+fn render_llama2(prompt: &ResolvedPromptList) -> String {
+    let mut output = String::new();
+    let mut system = None;
+    for (content, tags) in prompt.iter() {
+        match role(tags) {
+            Some(Role::System) => system = Some(text(content)),
+            Some(Role::Assistant) => output.push_str(&format!(" {} </s>", text(content))),
+            Some(Role::User) | None => {
+                // The system prompt goes inside the first instruction that follows it.
+                let user = match system.take() {
+                    Some(system) => format!("<<SYS>>\n{system}\n<</SYS>>\n\n{}", text(content)),
+                    None => text(content),
+                };
+                output.push_str(&format!("<s>[INST] {user} [/INST]"));
+            }
+        }
+    }
+
+    if let Some(system) = system {
+        output.push_str(&format!(
+            "<s>[INST] <<SYS>>\n{system}\n<</SYS>>\n\n [/INST]"
+        ));
+    }
+
+    output
+}
+
+fn render_alpaca(prompt: &ResolvedPromptList) -> String {
+    let mut sections = vec![];
+    for (content, tags) in prompt.iter() {
+        let section = match role(tags) {
+            Some(Role::System) => text(content),
+            Some(Role::Assistant) => format!("### Response:\n{}", text(content)),
+            Some(Role::User) | None => format!("### Instruction:\n{}", text(content)),
+        };
+        sections.push(section);
+    }
+
+    sections.push("### Response:\n".to_string());
+    sections.join("\n\n")
+}
+
+fn render_vicuna(prompt: &ResolvedPromptList) -> String {
+    let mut output = String::new();
+    for (content, tags) in prompt.iter() {
+        match role(tags) {
+            Some(Role::System) => output.push_str(&format!("{}\n\n", text(content))),
+            Some(Role::Assistant) => {
+                output.push_str(&format!("ASSISTANT: {}</s>\n", text(content)))
+            }
+            Some(Role::User) | None => output.push_str(&format!("USER: {}\n", text(content))),
+        }
+    }
+
+    output.push_str("ASSISTANT:");
+    output
+}
+
+fn render_plain(prompt: &ResolvedPromptList) -> String {
+    let mut lines = vec![];
+    let mut cue = false;
+    for (content, tags) in prompt.iter() {
+        let line = match role(tags) {
+            Some(Role::System) => format!("System: {}", text(content)),
+            Some(Role::User) => format!("User: {}", text(content)),
+            Some(Role::Assistant) => format!("Assistant: {}", text(content)),
+            None => text(content),
+        };
+
+        cue = matches!(role(tags), Some(Role::System | Role::User));
+        lines.push(line);
+    }
+
+    if cue {
+        lines.push("Assistant:".to_string());
+    }
+
+    lines.join("\n")
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl ChatTemplate for ChatFormat {
+    fn render(&self, prompt: &ResolvedPromptList) -> String {
+        match self {
+            ChatFormat::ChatMl => render_chatml(prompt),
+            ChatFormat::Llama2 => render_llama2(prompt),
+            ChatFormat::Alpaca => render_alpaca(prompt),
+            ChatFormat::Vicuna => render_vicuna(prompt),
+            ChatFormat::Plain => render_plain(prompt),
+        }
+    }
+}
+
+impl<F> ChatTemplate for F
+where
+    F: Fn(&ResolvedPromptList) -> String,
+{
+    fn render(&self, prompt: &ResolvedPromptList) -> String {
+        self(prompt)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prompt, FinalizablePrompt};
+
+    fn conversation() -> ResolvedPromptList {
+        prompt!(
+            system: "You are terse.",
+            user: "Hi!",
+            assistant: "Hello.",
+            user: "What is 2 + 2?"
+        )
+        .finalize()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_chatml() {
+        assert_eq!(
+            conversation().render_with(&ChatFormat::ChatMl),
+            "<|im_start|>system\nYou are terse.<|im_end|>\n\
+             <|im_start|>user\nHi!<|im_end|>\n\
+             <|im_start|>assistant\nHello.<|im_end|>\n\
+             <|im_start|>user\nWhat is 2 + 2?<|im_end|>\n\
+             <|im_start|>assistant\n"
+        );
+    }
+
+    #[test]
+    fn test_llama2() {
+        assert_eq!(
+            conversation().render_with(&ChatFormat::Llama2),
+            "<s>[INST] <<SYS>>\nYou are terse.\n<</SYS>>\n\nHi! [/INST] Hello. </s>\
+             <s>[INST] What is 2 + 2? [/INST]"
+        );
+    }
+
+    #[test]
+    fn test_alpaca_and_vicuna() {
+        assert_eq!(
+            conversation().render_with(&ChatFormat::Alpaca),
+            "You are terse.\n\n### Instruction:\nHi!\n\n### Response:\nHello.\n\n\
+             ### Instruction:\nWhat is 2 + 2?\n\n### Response:\n"
+        );
+        assert_eq!(
+            conversation().render_with(&ChatFormat::Vicuna),
+            "You are terse.\n\nUSER: Hi!\nASSISTANT: Hello.</s>\nUSER: What is 2 + 2?\nASSISTANT:"
+        );
+    }
+
+    #[test]
+    fn test_plain_is_the_default() {
+        assert_eq!(
+            String::from(conversation()),
+            "System: You are terse.\nUser: Hi!\nAssistant: Hello.\nUser: What is 2 + 2?\nAssistant:"
+        );
+
+        let prompt = crate::PromptList::new("Just text.", vec![])
+            .finalize()
+            .unwrap();
+        assert_eq!(String::from(prompt), "Just text.");
+    }
+
+    #[test]
+    fn test_custom_templates_and_model_selection() {
+        let template = |prompt: &ResolvedPromptList| prompt.iter().count().to_string();
+        assert_eq!(conversation().render_with(&template), "4");
+
+        assert_eq!(
+            ChatFormat::for_model("meta-llama/Llama-2-7b-chat-hf"),
+            ChatFormat::Llama2
+        );
+        assert_eq!(
+            ChatFormat::for_model("teknium/OpenHermes-2.5-Mistral-7B"),
+            ChatFormat::ChatMl
+        );
+        assert_eq!(
+            ChatFormat::for_model("lmsys/vicuna-13b-v1.5"),
+            ChatFormat::Vicuna
+        );
+        assert_eq!(
+            ChatFormat::for_model("gpt-3.5-turbo-instruct"),
+            ChatFormat::Plain
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use crate::PromptError;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// The content of a prompt message.
+///
+/// Content is either plain text or an ordered list of parts, which lets a message mix text with
+/// images for vision models.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// A part of a multimodal message.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentPart {
+    Text(String),
+    Image(Image),
+}
+
+/// An image sent as part of a message.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Image {
+    pub source: ImageSource,
+    pub detail: Option<ImageDetail>,
+}
+
+/// Where the image data comes from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSource {
+    /// An image the model fetches itself.
+    Url(String),
+
+    /// An image embedded in the request as base64-encoded data.
+    Base64 { mime_type: String, data: String },
+}
+
+/// How closely the model should look at the image.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDetail {
+    Auto,
+    Low,
+    High,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl Content {
+    /// Returns the text if the content is plain text.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Content::Text(text) => Some(text),
+            Content::Parts(_) => None,
+        }
+    }
+
+    /// Returns an iterator over the text of the content, skipping images.
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        let (text, parts) = match self {
+            Content::Text(text) => (Some(text.as_str()), [].iter()),
+            Content::Parts(parts) => (None, parts.iter()),
+        };
+
+        text.into_iter().chain(parts.filter_map(|part| match part {
+            ContentPart::Text(text) => Some(text.as_str()),
+            ContentPart::Image(_) => None,
+        }))
+    }
+
+    /// Returns a mutable iterator over the text of the content, skipping images.
+    pub(crate) fn texts_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        let (text, parts) = match self {
+            Content::Text(text) => (Some(text), [].iter_mut()),
+            Content::Parts(parts) => (None, parts.iter_mut()),
+        };
+
+        text.into_iter().chain(parts.filter_map(|part| match part {
+            ContentPart::Text(text) => Some(text),
+            ContentPart::Image(_) => None,
+        }))
+    }
+}
+
+impl Image {
+    /// Creates an image the model fetches from the given URL.
+    pub fn url(url: impl Into<String>) -> Self {
+        Self {
+            source: ImageSource::Url(url.into()),
+            detail: None,
+        }
+    }
+
+    /// Creates an image from base64-encoded data.
+    pub fn base64(mime_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self {
+            source: ImageSource::Base64 {
+                mime_type: mime_type.into(),
+                data: data.into(),
+            },
+            detail: None,
+        }
+    }
+
+    /// Creates an image from raw bytes, detecting the MIME type from their signature.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PromptError> {
+        let mime_type = detect_mime_type(bytes).ok_or(PromptError::UnsupportedImageFormat)?;
+        Ok(Self::base64(mime_type, STANDARD.encode(bytes)))
+    }
+
+    /// Loads an image from a local file and embeds it as base64-encoded data.
+    ///
+    /// The MIME type is detected from the file signature, falling back to the file extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, PromptError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        let mime_type = detect_mime_type(&bytes)
+            .or_else(|| {
+                let extension = path.extension()?.to_str()?.to_lowercase();
+                mime_type_from_extension(&extension)
+            })
+            .ok_or(PromptError::UnsupportedImageFormat)?;
+
+        Ok(Self::base64(mime_type, STANDARD.encode(bytes)))
+    }
+
+    /// Sets the detail level.
+    pub fn detail(mut self, detail: ImageDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Returns the URL the model should load the image from, which is a data URL for embedded images.
+    pub fn to_url(&self) -> String {
+        match &self.source {
+            ImageSource::Url(url) => url.clone(),
+            ImageSource::Base64 { mime_type, data } => format!("data:{mime_type};base64,{data}"),
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+fn detect_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn mime_type_from_extension(extension: &str) -> Option<&'static str> {
+    match extension {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl Default for Content {
+    fn default() -> Self {
+        Content::Text(String::new())
+    }
+}
+
+impl From<String> for Content {
+    fn from(text: String) -> Self {
+        Content::Text(text)
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        Content::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for Content {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        Content::Parts(parts)
+    }
+}
+
+impl From<String> for ContentPart {
+    fn from(text: String) -> Self {
+        ContentPart::Text(text)
+    }
+}
+
+impl From<&str> for ContentPart {
+    fn from(text: &str) -> Self {
+        ContentPart::Text(text.to_string())
+    }
+}
+
+impl From<Image> for ContentPart {
+    fn from(image: Image) -> Self {
+        ContentPart::Image(image)
+    }
+}
+
+impl PartialEq<str> for Content {
+    fn eq(&self, other: &str) -> bool {
+        self.as_text() == Some(other)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_detect_image_mime_types() {
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0x00];
+        let image = Image::from_bytes(&png).unwrap();
+        assert!(matches!(
+            &image.source,
+            ImageSource::Base64 { mime_type, .. } if mime_type == "image/png"
+        ));
+        assert!(image.to_url().starts_with("data:image/png;base64,"));
+
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0];
+        assert!(Image::from_bytes(&jpeg).is_ok());
+
+        assert!(matches!(
+            Image::from_bytes(b"not an image"),
+            Err(PromptError::UnsupportedImageFormat)
+        ));
+    }
+
+    #[test]
+    fn test_can_load_image_from_path() {
+        let path = std::env::temp_dir().join(format!("versa-image-{}.gif", std::process::id()));
+        fs::write(&path, b"GIF89a\x01\x00\x01\x00").unwrap();
+
+        let image = Image::from_path(&path).unwrap().detail(ImageDetail::Low);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(image.detail, Some(ImageDetail::Low));
+        assert!(image.to_url().starts_with("data:image/gif;base64,"));
+    }
+
+    #[test]
+    fn test_texts_skip_images() {
+        let content = Content::Parts(vec![
+            "What is in".into(),
+            Image::url("https://example.com/cat.png").into(),
+            "this image?".into(),
+        ]);
+
+        assert_eq!(
+            content.texts().collect::<Vec<_>>(),
+            vec!["What is in", "this image?"]
+        );
+        assert_eq!(content.as_text(), None);
+    }
+}
@@ -0,0 +1,430 @@
+use crate::{Prompt, PromptData, PromptError, PromptList, Role, Schema, Tag};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{fs, path::Path};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// A prompt loaded from a file, with the metadata that came with it.
+///
+/// YAML, TOML and JSON files have either a `prompt` field holding a single template, or a
+/// `messages` field holding a list of `role` and `content` pairs:
+///
+/// ```yaml
+/// name: classify
+/// schema:
+///   text: { type: string, required: true }
+/// messages:
+///   - role: system
+///     content: Classify the text into neutral, negative or positive.
+///   - role: user
+///     content: "{{text}}"
+/// ```
+///
+/// Markdown files take the same fields as front matter, between `---` lines for YAML or `+++`
+/// lines for TOML. The body is the template, or a list of messages if it has `# System`, `# User`
+/// or `# Assistant` headings. Text before the first heading becomes a message without a role.
+#[derive(Debug, Clone)]
+pub struct PromptDocument {
+    /// The name given in the file.
+    pub name: Option<String>,
+
+    /// The description given in the file.
+    pub description: Option<String>,
+
+    /// The other fields of the file, such as the model or the author.
+    pub metadata: Map<String, Value>,
+
+    /// The prompt itself.
+    pub body: PromptBody,
+}
+
+/// The prompt of a `PromptDocument`.
+#[derive(Debug, Clone)]
+pub enum PromptBody {
+    Single(Prompt),
+    List(PromptList),
+}
+
+/// The fields of a prompt file or of the front matter of a Markdown file.
+#[derive(Debug, Deserialize)]
+struct RawDocument {
+    #[serde(default)]
+    name: Option<String>,
+
+    #[serde(default)]
+    description: Option<String>,
+
+    #[serde(default)]
+    strict: bool,
+
+    #[serde(default)]
+    schema: Option<Schema>,
+
+    #[serde(default)]
+    prompt: Option<String>,
+
+    #[serde(default)]
+    messages: Option<Vec<RawMessage>>,
+
+    #[serde(flatten)]
+    metadata: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawMessage {
+    #[serde(default)]
+    role: Option<Role>,
+    content: String,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl PromptDocument {
+    /// Loads a prompt file, picking the format from its extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, PromptError> {
+        let path = path.as_ref();
+        let parse = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Self::from_yaml,
+            Some("toml") => Self::from_toml,
+            Some("json") => Self::from_json,
+            Some("md" | "markdown") => Self::from_markdown,
+            _ => return Err(PromptError::UnsupportedFile(path.to_path_buf())),
+        };
+
+        fs::read_to_string(path)
+            .map_err(PromptError::from)
+            .and_then(|text| parse(&text))
+            .map_err(|error| PromptError::InFile {
+                path: path.to_path_buf(),
+                source: Box::new(error),
+            })
+    }
+
+    /// Parses a prompt from YAML.
+    pub fn from_yaml(text: &str) -> Result<Self, PromptError> {
+        Self::from_raw(serde_yaml::from_str(text)?, None)
+    }
+
+    /// Parses a prompt from TOML.
+    pub fn from_toml(text: &str) -> Result<Self, PromptError> {
+        Self::from_raw(toml::from_str(text)?, None)
+    }
+
+    /// Parses a prompt from JSON.
+    pub fn from_json(text: &str) -> Result<Self, PromptError> {
+        Self::from_raw(serde_json::from_str(text)?, None)
+    }
+
+    /// Parses a prompt from Markdown with optional front matter.
+    pub fn from_markdown(text: &str) -> Result<Self, PromptError> {
+        let (fence, front_matter, body) = split_front_matter(text)?;
+        let raw = match fence {
+            Some("+++") => toml::from_str(front_matter)?,
+            Some(_) if !front_matter.trim().is_empty() => serde_yaml::from_str(front_matter)?,
+            _ => serde_json::from_value(Value::Object(Map::new()))?,
+        };
+
+        Self::from_raw(raw, Some(parse_body(body)))
+    }
+
+    /// Checks the templates and the schema of the prompt, without giving values.
+    pub fn validate(&self) -> Result<(), PromptError> {
+        match &self.body {
+            PromptBody::Single(prompt) => prompt.validate(),
+            PromptBody::List(prompt) => prompt.validate(),
+        }
+    }
+
+    /// Returns the prompt, failing if it is a list of messages.
+    pub fn into_prompt(self) -> Result<Prompt, PromptError> {
+        match self.body {
+            PromptBody::Single(prompt) => Ok(prompt),
+            PromptBody::List(_) => {
+                Err(PromptError::NotASinglePrompt(self.name.unwrap_or_default()))
+            }
+        }
+    }
+
+    /// Returns the prompt as a list of messages. A single prompt becomes a message without a role.
+    pub fn into_prompt_list(self) -> PromptList {
+        match self.body {
+            PromptBody::Single(prompt) => prompt.into(),
+            PromptBody::List(prompt) => prompt,
+        }
+    }
+
+    /// Builds the document from the fields, taking the prompt from `body` if given.
+    fn from_raw(raw: RawDocument, body: Option<PromptBody>) -> Result<Self, PromptError> {
+        let body = match (body, raw.prompt, raw.messages) {
+            (None, Some(prompt), None) => PromptBody::Single(Prompt::new(prompt)),
+            (None, None, Some(messages)) if !messages.is_empty() => {
+                let mut prompt = PromptList::default();
+                for message in messages {
+                    let tags = message.role.into_iter().map(Tag::Role).collect();
+                    prompt.add_message(message.content, tags);
+                }
+
+                PromptBody::List(prompt)
+            }
+            (Some(body), None, None) => body,
+            (Some(_), _, _) => {
+                return Err(PromptError::InvalidFile(
+                    "Markdown front matter cannot have `prompt` or `messages`".to_string(),
+                ))
+            }
+            _ => {
+                return Err(PromptError::InvalidFile(
+                    "expected either a `prompt` or a non-empty `messages` field".to_string(),
+                ))
+            }
+        };
+
+        let body = match body {
+            PromptBody::Single(prompt) => {
+                PromptBody::Single(configure(prompt, raw.strict, raw.schema))
+            }
+            PromptBody::List(prompt) => PromptBody::List(configure(prompt, raw.strict, raw.schema)),
+        };
+
+        Ok(Self {
+            name: raw.name,
+            description: raw.description,
+            metadata: raw.metadata,
+            body,
+        })
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+fn configure<T>(prompt: PromptData<T>, strict: bool, schema: Option<Schema>) -> PromptData<T> {
+    let prompt = prompt.strict(strict);
+    match schema {
+        Some(schema) => prompt.schema(schema),
+        None => prompt,
+    }
+}
+
+/// Splits Markdown into the fence of its front matter, the front matter and the body.
+fn split_front_matter(text: &str) -> Result<(Option<&str>, &str, &str), PromptError> {
+    let text = text.trim_start_matches('\u{feff}');
+    for fence in ["---", "+++"] {
+        let rest = match text.strip_prefix(fence).and_then(|rest| {
+            rest.strip_prefix('\n')
+                .or_else(|| rest.strip_prefix("\r\n"))
+        }) {
+            Some(rest) => rest,
+            None => continue,
+        };
+
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == fence {
+                return Ok((Some(fence), &rest[..offset], &rest[offset + line.len()..]));
+            }
+
+            offset += line.len();
+        }
+
+        return Err(PromptError::InvalidFile(
+            "front matter is not closed".to_string(),
+        ));
+    }
+
+    Ok((None, "", text))
+}
+
+/// Splits the Markdown body into messages at role headings, ignoring headings in code blocks.
+fn parse_body(body: &str) -> PromptBody {
+    let mut sections: Vec<(Option<Role>, String)> = vec![(None, String::new())];
+    let mut fenced = false;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fenced = !fenced;
+        }
+
+        match role_heading(line).filter(|_| !fenced) {
+            Some(role) => sections.push((Some(role), String::new())),
+            None => {
+                if let Some((_, text)) = sections.last_mut() {
+                    text.push_str(line);
+                }
+            }
+        }
+    }
+
+    if sections.len() == 1 {
+        return PromptBody::Single(Prompt::new(body.trim()));
+    }
+
+    let mut prompt = PromptList::default();
+    for (role, text) in sections {
+        if role.is_none() && text.trim().is_empty() {
+            continue;
+        }
+
+        prompt.add_message(text.trim(), role.into_iter().map(Tag::Role).collect());
+    }
+
+    PromptBody::List(prompt)
+}
+
+/// Returns the role of a `# System`, `# User` or `# Assistant` heading of any level.
+fn role_heading(line: &str) -> Option<Role> {
+    let line = line.trim_end();
+    let level = line.len() - line.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    match line[level..]
+        .strip_prefix(' ')?
+        .trim()
+        .to_lowercase()
+        .as_str()
+    {
+        "system" => Some(Role::System),
+        "user" => Some(Role::User),
+        "assistant" => Some(Role::Assistant),
+        _ => None,
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Content, FinalizablePrompt, VarSpec, VarType};
+    use serde_json::json;
+
+    fn roles(prompt: &PromptList) -> Vec<Option<String>> {
+        prompt
+            .iter()
+            .map(|(_, tags)| {
+                tags.iter().find_map(|tag| match tag {
+                    Tag::Role(role) => Some(format!("{role:?}")),
+                    Tag::Pattern(_) => None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_can_load_structured_formats() -> anyhow::Result<()> {
+        let yaml = PromptDocument::from_yaml(
+            r#"
+name: classify
+model: gpt-4
+schema:
+  text: { type: string, required: true }
+messages:
+  - role: system
+    content: Classify the text.
+  - role: user
+    content: "{{text}}"
+"#,
+        )?;
+
+        assert_eq!(yaml.name.as_deref(), Some("classify"));
+        assert_eq!(
+            Value::Object(yaml.metadata.clone()),
+            json!({"model": "gpt-4"})
+        );
+
+        let prompt = yaml.into_prompt_list();
+        assert_eq!(
+            prompt.get_schema(),
+            Some(&Schema::new().var("text", VarSpec::new(VarType::String).required()))
+        );
+        assert_eq!(
+            roles(&prompt),
+            vec![Some("System".into()), Some("User".into())]
+        );
+
+        let toml = PromptDocument::from_toml(
+            "name = \"greet\"\nstrict = true\nprompt = \"Hello {{name}}!\"\n",
+        )?;
+        let mut prompt = toml.into_prompt()?;
+        assert!(matches!(
+            prompt.format_value(&json!({"nmae": "Ada"})),
+            Err(PromptError::UnusedVars(_))
+        ));
+
+        let json = PromptDocument::from_json(r#"{"messages": [{"content": "Hi {{name}}"}]}"#)?;
+        assert_eq!(roles(&json.clone().into_prompt_list()), vec![None]);
+        assert!(matches!(
+            json.into_prompt(),
+            Err(PromptError::NotASinglePrompt(_))
+        ));
+
+        assert!(matches!(
+            PromptDocument::from_yaml("name: empty"),
+            Err(PromptError::InvalidFile(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_can_load_markdown() -> anyhow::Result<()> {
+        let document = PromptDocument::from_markdown(
+            "---\nname: review\ndescription: Reviews code.\n---\n\
+             You review code.\n\n\
+             # System\nBe strict.\n\n\
+             ```md\n# User\n```\n\n\
+             ## user\n{{code}}\n",
+        )?;
+
+        assert_eq!(document.description.as_deref(), Some("Reviews code."));
+        let prompt = document.into_prompt_list();
+        assert_eq!(
+            roles(&prompt),
+            vec![None, Some("System".into()), Some("User".into())]
+        );
+
+        let texts: Vec<_> = prompt.iter().map(|(content, _)| content.clone()).collect();
+        assert_eq!(texts[1], Content::from("Be strict.\n\n```md\n# User\n```"));
+
+        let document =
+            PromptDocument::from_markdown("+++\nname = \"plain\"\n+++\n\nSummarize {{text}}.\n")?;
+        assert_eq!(document.name.as_deref(), Some("plain"));
+        assert_eq!(String::from(document.into_prompt()?), "Summarize {{text}}.");
+
+        let document = PromptDocument::from_markdown("Just {{text}}.")?;
+        assert!(matches!(document.body, PromptBody::Single(_)));
+
+        assert!(matches!(
+            PromptDocument::from_markdown("---\nname: open\n"),
+            Err(PromptError::InvalidFile(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validates_templates_and_schema() -> anyhow::Result<()> {
+        let document = PromptDocument::from_yaml("prompt: \"{{text | shout}}\"")?;
+        assert!(matches!(
+            document.validate(),
+            Err(PromptError::UnknownFilter(name)) if name == "shout"
+        ));
+
+        let document = PromptDocument::from_yaml(
+            "prompt: \"{{count}}\"\nschema:\n  count: { type: number, default: many }\n",
+        )?;
+        assert!(matches!(
+            document.validate(),
+            Err(PromptError::InvalidType { name, .. }) if name == "count"
+        ));
+        Ok(())
+    }
+}
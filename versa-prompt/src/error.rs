@@ -1,3 +1,5 @@
+use crate::{VarType, Variable};
+use std::path::PathBuf;
 use thiserror::Error;
 
 //-------------------------------------------------------------------------------------------------
@@ -6,9 +8,81 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum PromptError {
-    #[error("Prompt contains unresolved variables.")]
-    UnresolvedVars,
+    #[error("Prompt contains unresolved variables: {}", join(.0))]
+    UnresolvedVars(Vec<Variable>),
 
-    #[error("Regex error: {0}")]
-    RegexError(#[from] regex::Error),
+    #[error("Variables are not used by the prompt: {}", .0.join(", "))]
+    UnusedVars(Vec<String>),
+
+    #[error("Unknown template filter `{0}`.")]
+    UnknownFilter(String),
+
+    #[error("Invalid argument for template filter `{0}`.")]
+    InvalidFilterArgument(String),
+
+    #[error("Template variable `{name}` must be a {expected}.")]
+    InvalidType { name: String, expected: VarType },
+
+    #[error("Required template variable `{0}` was not given.")]
+    MissingRequiredVar(String),
+
+    #[error("Template path `{0}` does not exist in the given values.")]
+    MissingPath(String),
+
+    #[error("Template variable `{0}` is used in `#each` but is not a list.")]
+    NotAList(String),
+
+    #[error("No prompt variant matches model `{0}`.")]
+    NoPromptForModel(String),
+
+    #[error("Template variables must be given as a JSON object.")]
+    InvalidContext,
+
+    #[error("Serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Unsupported prompt file `{0}`, expected YAML, TOML, JSON or Markdown.")]
+    UnsupportedFile(PathBuf),
+
+    #[error("Invalid prompt file: {0}")]
+    InvalidFile(String),
+
+    #[error("Invalid prompt file `{path}`: {source}")]
+    InFile {
+        path: PathBuf,
+        source: Box<PromptError>,
+    },
+
+    #[error("Expected a single prompt but `{0}` has a list of messages.")]
+    NotASinglePrompt(String),
+
+    #[error("No prompt named `{0}` in the library.")]
+    UnknownPrompt(String),
+
+    #[error("More than one prompt is named `{0}` in the library.")]
+    DuplicatePrompt(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Unsupported image format, expected PNG, JPEG, GIF or WebP.")]
+    UnsupportedImageFormat,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+fn join(variables: &[Variable]) -> String {
+    variables
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
@@ -3,11 +3,30 @@
 //! This module contains implementation of the prompt templating feature.
 //! This lets users create reusable prompts.
 
+#[cfg(test)]
+extern crate self as versa_prompt;
+
+mod chat;
+mod content;
+mod document;
 mod error;
+mod library;
 mod macros;
 mod prompt;
+mod schema;
+mod select;
+mod template;
 mod traits;
 
+pub use chat::*;
+pub use content::*;
+pub use document::*;
 pub use error::*;
+pub use library::*;
 pub use prompt::*;
+pub use schema::*;
+pub use select::*;
+pub use template::*;
 pub use traits::*;
+#[cfg(feature = "derive")]
+pub use versa_common::PromptTemplate;
@@ -0,0 +1,290 @@
+use crate::{Prompt, PromptDocument, PromptError, PromptList};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{PoisonError, RwLock},
+    time::SystemTime,
+};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// The prompts of a directory, indexed by name.
+///
+/// Every YAML, TOML, JSON and Markdown file under the directory is loaded, see `PromptDocument`
+/// for their layout. A prompt is named by its `name` field, or else by its path relative to the
+/// directory without the extension, like `support/greeting`. All prompts are validated when the
+/// library is loaded, so a broken template fails early instead of at call time.
+///
+/// With hot reload, the directory is checked for changes whenever a prompt is looked up, which
+/// lets prompts be edited while the program runs during development.
+#[derive(Debug)]
+pub struct PromptLibrary {
+    dir: PathBuf,
+    hot_reload: bool,
+    state: RwLock<LibraryState>,
+}
+
+#[derive(Debug, Default)]
+struct LibraryState {
+    prompts: BTreeMap<String, PromptDocument>,
+    stamps: Vec<Stamp>,
+}
+
+/// What a file looked like when it was loaded.
+type Stamp = (PathBuf, SystemTime, u64);
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl PromptLibrary {
+    /// Loads and validates the prompts of a directory.
+    pub fn load(dir: impl Into<PathBuf>) -> Result<Self, PromptError> {
+        let dir = dir.into();
+        let state = load_state(&dir)?;
+        Ok(Self {
+            dir,
+            hot_reload: false,
+            state: RwLock::new(state),
+        })
+    }
+
+    /// Reloads the prompts when their files change.
+    pub fn hot_reload(mut self, hot_reload: bool) -> Self {
+        self.hot_reload = hot_reload;
+        self
+    }
+
+    /// Returns the directory of the library.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Loads the prompts again. The current prompts are kept if any file is invalid.
+    pub fn reload(&self) -> Result<(), PromptError> {
+        let state = load_state(&self.dir)?;
+        *self.state.write().unwrap_or_else(PoisonError::into_inner) = state;
+        Ok(())
+    }
+
+    /// Returns the names of the prompts, in order.
+    pub fn names(&self) -> Result<Vec<String>, PromptError> {
+        self.refresh()?;
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(state.prompts.keys().cloned().collect())
+    }
+
+    /// Returns the prompt with the given name, with its metadata.
+    pub fn get(&self, name: &str) -> Result<PromptDocument, PromptError> {
+        self.refresh()?;
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        state
+            .prompts
+            .get(name)
+            .cloned()
+            .ok_or_else(|| PromptError::UnknownPrompt(name.to_string()))
+    }
+
+    /// Returns the single prompt with the given name.
+    pub fn prompt(&self, name: &str) -> Result<Prompt, PromptError> {
+        self.get(name)?.into_prompt()
+    }
+
+    /// Returns the prompt with the given name as a list of messages.
+    pub fn prompt_list(&self, name: &str) -> Result<PromptList, PromptError> {
+        Ok(self.get(name)?.into_prompt_list())
+    }
+
+    /// Reloads the prompts if hot reload is on and a file was added, removed or changed.
+    fn refresh(&self) -> Result<(), PromptError> {
+        if !self.hot_reload {
+            return Ok(());
+        }
+
+        let stamps = stamps(&self.dir)?;
+        let changed = self
+            .state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stamps
+            != stamps;
+        if changed {
+            self.reload()?;
+        }
+
+        Ok(())
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+fn load_state(dir: &Path) -> Result<LibraryState, PromptError> {
+    let stamps = stamps(dir)?;
+    let mut prompts = BTreeMap::new();
+    for (path, _, _) in stamps.iter() {
+        let document = PromptDocument::from_path(path)?;
+        document.validate().map_err(|error| PromptError::InFile {
+            path: path.clone(),
+            source: Box::new(error),
+        })?;
+
+        let name = match &document.name {
+            Some(name) => name.clone(),
+            None => name_from_path(dir, path),
+        };
+
+        if prompts.insert(name.clone(), document).is_some() {
+            return Err(PromptError::DuplicatePrompt(name));
+        }
+    }
+
+    Ok(LibraryState { prompts, stamps })
+}
+
+/// Lists the prompt files under the directory, sorted by path.
+fn stamps(dir: &Path) -> Result<Vec<Stamp>, PromptError> {
+    let mut stamps = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let metadata = entry.metadata()?;
+            if hidden {
+                continue;
+            }
+
+            if metadata.is_dir() {
+                dirs.push(path);
+            } else if is_prompt_file(&path) {
+                stamps.push((path, metadata.modified()?, metadata.len()));
+            }
+        }
+    }
+
+    stamps.sort();
+    Ok(stamps)
+}
+
+fn is_prompt_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("yaml" | "yml" | "toml" | "json" | "md" | "markdown")
+    )
+}
+
+/// Names a prompt by its path relative to the directory, without the extension.
+fn name_from_path(dir: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(dir).unwrap_or(path).with_extension("");
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FinalizablePrompt;
+
+    /// A directory that is removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("versa-prompt-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("support")).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, path: &str, text: &str) {
+            fs::write(self.0.join(path), text).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_can_index_a_directory() -> anyhow::Result<()> {
+        let dir = TempDir::new("index");
+        dir.write("summarize.yaml", "prompt: \"Summarize {{text}}.\"");
+        dir.write("support/greeting.md", "# System\nBe kind.\n# User\nHi!");
+        dir.write(
+            "other.toml",
+            "name = \"farewell\"\nprompt = \"Bye {{name}}.\"",
+        );
+        dir.write("notes.txt", "Not a prompt.");
+
+        let library = PromptLibrary::load(&dir.0)?;
+        assert_eq!(
+            library.names()?,
+            vec!["farewell", "summarize", "support/greeting"]
+        );
+
+        let prompt = library
+            .prompt("summarize")?
+            .resolve_with(&serde_json::json!({"text": "the news"}))?;
+        assert_eq!(String::from(prompt), "Summarize the news.");
+        assert_eq!(library.prompt_list("support/greeting")?.iter().count(), 2);
+        assert!(matches!(
+            library.get("missing"),
+            Err(PromptError::UnknownPrompt(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validates_on_load() {
+        let dir = TempDir::new("validate");
+        dir.write("broken.yaml", "prompt: \"{{text | shout}}\"");
+        assert!(matches!(
+            PromptLibrary::load(&dir.0),
+            Err(PromptError::InFile { source, .. }) if matches!(*source, PromptError::UnknownFilter(_))
+        ));
+
+        let dir = TempDir::new("duplicate");
+        dir.write("a.yaml", "name: same\nprompt: A");
+        dir.write("b.json", r#"{"name": "same", "prompt": "B"}"#);
+        assert!(matches!(
+            PromptLibrary::load(&dir.0),
+            Err(PromptError::DuplicatePrompt(name)) if name == "same"
+        ));
+    }
+
+    #[test]
+    fn test_can_hot_reload() -> anyhow::Result<()> {
+        let dir = TempDir::new("reload");
+        dir.write("greeting.yaml", "prompt: Hello.");
+
+        let library = PromptLibrary::load(&dir.0)?.hot_reload(true);
+        assert_eq!(String::from(library.prompt("greeting")?), "Hello.");
+
+        dir.write("greeting.yaml", "prompt: Hello there.");
+        dir.write("farewell.yaml", "prompt: Bye.");
+        assert_eq!(String::from(library.prompt("greeting")?), "Hello there.");
+        assert_eq!(library.names()?, vec!["farewell", "greeting"]);
+
+        // A broken edit fails the lookup until it is fixed.
+        dir.write("farewell.yaml", "prompt: \"{{name | shout}}\"");
+        assert!(library.get("greeting").is_err());
+        dir.write("farewell.yaml", "prompt: \"Bye {{name}}.\"");
+        assert_eq!(String::from(library.prompt("farewell")?), "Bye {{name}}.");
+        Ok(())
+    }
+}
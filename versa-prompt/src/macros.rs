@@ -4,6 +4,9 @@
 
 // TODO(nyprothegeek): Implement output which is simply grabbing a
 /// This macro is used to create a prompt.
+///
+/// Chat messages can mix text and images by listing their parts in braces, for example
+/// `user: { "What is in this image?", Image::url("https://example.com/cat.png") }`.
 #[macro_export]
 macro_rules! prompt {
     ($str:literal) => {
@@ -24,6 +27,12 @@ macro_rules! prompt {
             vec![ $crate::Tag::Role($crate::Role::System)
         ]);
     };
+    (@chat $prompt:ident, system: { $( $part:expr ),+ $(,)? }) => {
+        $prompt.add_message(
+            $crate::Content::Parts(vec![ $( $crate::ContentPart::from($part) ),+ ]),
+            vec![ $crate::Tag::Role($crate::Role::System) ]
+        );
+    };
     (@chat $prompt:ident, system: $system:literal ) => {
         $prompt.add_message($system, vec![ $crate::Tag::Role($crate::Role::System) ]);
     };
@@ -33,6 +42,12 @@ macro_rules! prompt {
             vec![ $crate::Tag::Role($crate::Role::User)
         ]);
     };
+    (@chat $prompt:ident, user: { $( $part:expr ),+ $(,)? }) => {
+        $prompt.add_message(
+            $crate::Content::Parts(vec![ $( $crate::ContentPart::from($part) ),+ ]),
+            vec![ $crate::Tag::Role($crate::Role::User) ]
+        );
+    };
     (@chat $prompt:ident, user: $user:literal ) => {
         $prompt.add_message($user, vec![ $crate::Tag::Role($crate::Role::User) ]);
     };
@@ -42,6 +57,12 @@ macro_rules! prompt {
             vec![ $crate::Tag::Role($crate::Role::Assistant)
         ]);
     };
+    (@chat $prompt:ident, assistant: { $( $part:expr ),+ $(,)? }) => {
+        $prompt.add_message(
+            $crate::Content::Parts(vec![ $( $crate::ContentPart::from($part) ),+ ]),
+            vec![ $crate::Tag::Role($crate::Role::Assistant) ]
+        );
+    };
     (@chat $prompt:ident, assistant: $assistant:literal ) => {
         $prompt.add_message($assistant, vec![ $crate::Tag::Role($crate::Role::Assistant) ]);
     };
@@ -60,9 +81,23 @@ macro_rules! map {
     }};
 }
 
-// TODO(nyprothegeek): Implement.
 /// This macro is used to create a prompt map that allows selection of a prompt based on the model.
+///
+/// Keys are exact model ids or patterns like `"gpt-3.5-*"`, and `_` sets the fallback.
 #[macro_export]
 macro_rules! select {
-    () => {};
+    (@variant $select:ident $(,)?) => {};
+    (@variant $select:ident, _ => $fallback:expr $(, $( $rest:tt )* )?) => {
+        $select = $select.fallback($fallback);
+        $crate::select!(@variant $select $(, $( $rest )* )?);
+    };
+    (@variant $select:ident, $pattern:literal => $prompt:expr $(, $( $rest:tt )* )?) => {
+        $select = $select.variant($pattern, $prompt);
+        $crate::select!(@variant $select $(, $( $rest )* )?);
+    };
+    ($( $variants:tt )*) => {{
+        let mut select = $crate::PromptSelect::new();
+        $crate::select!(@variant select, $( $variants )*);
+        select
+    }};
 }
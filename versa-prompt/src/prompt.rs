@@ -1,7 +1,10 @@
-use crate::{FinalizablePrompt, FinalizedPrompt, PromptError};
-use regex::Regex;
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Debug, vec};
+use crate::{
+    ChatFormat, Content, FinalizablePrompt, FinalizedPrompt, PromptError, Schema, Template,
+    Variable,
+};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::{collections::BTreeSet, fmt::Debug, vec};
 use versa_common::{pattern::Pattern, traits::Config};
 
 //-------------------------------------------------------------------------------------------------
@@ -9,10 +12,10 @@ use versa_common::{pattern::Pattern, traits::Config};
 //-------------------------------------------------------------------------------------------------
 
 pub type Tags = Vec<Tag>;
-pub type Prompt = PromptData<String>;
-pub type PromptList = PromptData<Vec<(String, Tags)>>;
-pub type ResolvedPrompt = ResolvedPromptData<String>;
-pub type ResolvedPromptList = ResolvedPromptData<Vec<(String, Tags)>>;
+pub type Prompt = PromptData<Template>;
+pub type PromptList = PromptData<Vec<PromptMessage>>;
+pub type ResolvedPrompt = ResolvedPromptData<Template>;
+pub type ResolvedPromptList = ResolvedPromptData<Vec<PromptMessage>>;
 
 //-------------------------------------------------------------------------------------------------
 // Types
@@ -24,12 +27,33 @@ pub type ResolvedPromptList = ResolvedPromptData<Vec<(String, Tags)>>;
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct PromptData<T> {
     data: T,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    strict: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema: Option<Schema>,
+
+    /// The names of the variables given so far, checked against the required variables.
+    #[serde(skip)]
+    given: BTreeSet<String>,
 }
 
 /// A resolved prompt is a prompt that has been resolved.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ResolvedPromptData<T>(PromptData<T>);
 
+/// A message of a `PromptList`.
+///
+/// The text of the message is parsed into templates once, when the message is added. The content
+/// holds the rendered templates.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(from = "(Content, Tags)")]
+pub struct PromptMessage {
+    message: (Content, Tags),
+    templates: Vec<Template>,
+}
+
 /// A tag that describes the prompt message identity.
 /// This becomes useful later when converting prompts to strings.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,52 +73,266 @@ pub enum Role {
 
 /// An iterator over the messages of a `PromptList`.
 pub struct PromptListIter<'a> {
-    iter: std::slice::Iter<'a, (String, Tags)>,
+    iter: std::slice::Iter<'a, PromptMessage>,
 }
 
 //-------------------------------------------------------------------------------------------------
 // Methods
 //-------------------------------------------------------------------------------------------------
 
+impl<T> PromptData<T> {
+    /// Makes formatting fail with `PromptError::UnusedVars` when given variables that the prompt
+    /// does not use, which catches typos in variable names.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Attaches a schema that checks the given values and provides defaults for the variables.
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Returns the schema of the prompt.
+    pub fn get_schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+
+    /// Remembers the names of the values of a successful format call.
+    fn record_given(&mut self, values: &Value) {
+        if let Some(fields) = values.as_object() {
+            self.given.extend(fields.keys().cloned());
+        }
+    }
+}
+
 impl Prompt {
     /// Creates a new prompt.
     pub fn new(message: impl Into<String>) -> Self {
         Self {
-            data: message.into(),
+            data: Template::parse(&message.into()),
+            strict: false,
+            schema: None,
+            given: BTreeSet::new(),
         }
     }
+
+    /// Checks the filters of the template and the defaults of the schema, without giving values.
+    pub fn validate(&self) -> Result<(), PromptError> {
+        self.data.validate()?;
+        validate_schema(self.schema.as_ref())
+    }
 }
 
 impl PromptList {
     /// Creates a new prompt.
-    pub fn new(message: impl Into<String>, tags: Vec<Tag>) -> Self {
+    ///
+    /// The message can be plain text or a list of text and image parts.
+    pub fn new(message: impl Into<Content>, tags: Vec<Tag>) -> Self {
         Self {
-            data: vec![(message.into(), tags)],
+            data: vec![PromptMessage::new(message.into(), tags)],
+            strict: false,
+            schema: None,
+            given: BTreeSet::new(),
         }
     }
 
     /// Adds a message to the prompt.
-    pub fn add_message(&mut self, message: impl Into<String>, tags: Vec<Tag>) {
-        self.data.push((message.into(), tags));
+    pub fn add_message(&mut self, message: impl Into<Content>, tags: Vec<Tag>) {
+        self.data.push(PromptMessage::new(message.into(), tags));
     }
 
     /// Returns an iterator over the messages of the prompt.
-    pub fn iter(&self) -> PromptListIter {
+    pub fn iter(&self) -> PromptListIter<'_> {
         PromptListIter {
             iter: self.data.iter(),
         }
     }
+
+    /// Checks the filters of every message and the defaults of the schema, without giving values.
+    pub fn validate(&self) -> Result<(), PromptError> {
+        for template in self
+            .data
+            .iter()
+            .flat_map(|message| message.templates.iter())
+        {
+            template.validate()?;
+        }
+
+        validate_schema(self.schema.as_ref())
+    }
+}
+
+impl PromptMessage {
+    /// Creates a new message, parsing its text into templates.
+    pub fn new(content: Content, tags: Tags) -> Self {
+        let templates = content.texts().map(Template::parse).collect();
+        let mut message = Self {
+            message: (content, tags),
+            templates,
+        };
+
+        message.render();
+        message
+    }
+
+    /// Returns the content of the message.
+    pub fn content(&self) -> &Content {
+        &self.message.0
+    }
+
+    /// Returns the tags of the message.
+    pub fn tags(&self) -> &Tags {
+        &self.message.1
+    }
+
+    /// Returns the templates of the text in the message, in order.
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    /// Checks if the message has unresolved variables.
+    pub fn has_variables(&self) -> bool {
+        self.templates.iter().any(Template::has_variables)
+    }
+
+    /// Returns the unresolved placeholders of the message, with `message` set to `index`.
+    pub fn variables(&self, index: usize) -> Vec<Variable> {
+        self.templates
+            .iter()
+            .enumerate()
+            .flat_map(|(part, template)| {
+                template
+                    .variables()
+                    .into_iter()
+                    .map(move |variable| Variable {
+                        message: index,
+                        part,
+                        ..variable
+                    })
+            })
+            .collect()
+    }
+
+    /// Resolves the variables found in the map and renders the content again.
+    fn resolve(&mut self, values: &Value) -> Result<(), PromptError> {
+        if !self.has_variables() {
+            return Ok(());
+        }
+
+        for template in self.templates.iter_mut() {
+            template.resolve(values)?;
+        }
+
+        self.render();
+        Ok(())
+    }
+
+    /// Resolves the missing variables that have an inline default.
+    fn resolve_defaults(&mut self) -> Result<(), PromptError> {
+        if !self.has_variables() {
+            return Ok(());
+        }
+
+        for template in self.templates.iter_mut() {
+            template.resolve_defaults()?;
+        }
+
+        self.render();
+        Ok(())
+    }
+
+    /// Writes the rendered templates back into the content.
+    fn render(&mut self) {
+        for (text, template) in self.message.0.texts_mut().zip(self.templates.iter()) {
+            *text = template.render();
+        }
+    }
 }
 
 impl ResolvedPromptList {
     /// Returns an iterator over the messages of the prompt.
-    pub fn iter(&self) -> PromptListIter {
+    pub fn iter(&self) -> PromptListIter<'_> {
         PromptListIter {
             iter: self.0.data.iter(),
         }
     }
 }
 
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Fails if `values` has fields that none of the templates refer to.
+fn check_unused<'a>(
+    values: &Value,
+    templates: impl IntoIterator<Item = &'a Template>,
+) -> Result<(), PromptError> {
+    let fields = match values.as_object() {
+        Some(fields) => fields,
+        None => return Err(PromptError::InvalidContext),
+    };
+
+    let mut names = BTreeSet::new();
+    for template in templates {
+        names.extend(template.referenced_names());
+    }
+
+    let unused: Vec<_> = fields
+        .keys()
+        .filter(|key| !names.contains(key.as_str()))
+        .cloned()
+        .collect();
+
+    if !unused.is_empty() {
+        return Err(PromptError::UnusedVars(unused));
+    }
+
+    Ok(())
+}
+
+/// Fails if a required variable of the schema was never given. Required variables that the prompt
+/// still refers to are reported as unresolved.
+fn check_required(
+    schema: Option<&Schema>,
+    given: &BTreeSet<String>,
+    variables: Vec<Variable>,
+) -> Result<(), PromptError> {
+    let schema = match schema {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+
+    for (name, spec) in schema.iter() {
+        if !spec.required || given.contains(name) {
+            continue;
+        }
+
+        let unresolved: Vec<_> = variables
+            .iter()
+            .filter(|variable| variable.name == name)
+            .cloned()
+            .collect();
+
+        if unresolved.is_empty() {
+            return Err(PromptError::MissingRequiredVar(name.to_string()));
+        }
+
+        return Err(PromptError::UnresolvedVars(unresolved));
+    }
+
+    Ok(())
+}
+
+/// Fails if a default of the schema does not have the declared type.
+fn validate_schema(schema: Option<&Schema>) -> Result<(), PromptError> {
+    match schema {
+        Some(schema) => schema.validate(&schema.defaults()),
+        None => Ok(()),
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Trait Implementations
 //-------------------------------------------------------------------------------------------------
@@ -102,31 +340,36 @@ impl ResolvedPromptList {
 impl FinalizablePrompt for Prompt {
     type FinalizedPrompt = ResolvedPrompt;
 
-    fn has_unresolved_vars(&self) -> Result<bool, PromptError> {
-        let re = Regex::new(r"\{\{(?<var>[a-zA-Z_][a-zA-Z0-9_]*)\}\}")?;
-        if re.is_match(&self.data) {
-            return Ok(true);
+    fn variables(&self) -> Vec<Variable> {
+        self.data.variables()
+    }
+
+    fn format_value(&mut self, values: &Value) -> Result<(), PromptError> {
+        if let Some(schema) = &self.schema {
+            schema.validate(values)?;
         }
 
-        Ok(false)
-    }
+        if self.strict {
+            check_unused(values, [&self.data])?;
+        }
 
-    fn resolve_var(&mut self, var: &str, value: &str) -> Result<(), PromptError> {
-        let re = Regex::new(&format!(r"\{{\{{(?<var>{})\}}\}}", var))?;
-        self.data = re.replace_all(&self.data, value).into();
+        self.data.resolve(values)?;
+        self.record_given(values);
         Ok(())
     }
 
-    fn format(&mut self, map: HashMap<&str, &str>) -> Result<(), PromptError> {
-        for (var, value) in map {
-            self.resolve_var(var, value)?;
+    fn finalize(mut self) -> Result<Self::FinalizedPrompt, PromptError> {
+        check_required(self.schema.as_ref(), &self.given, self.variables())?;
+
+        // Inline defaults take precedence over the defaults of the schema.
+        self.data.resolve_defaults()?;
+        if let Some(schema) = &self.schema {
+            self.data.resolve(&schema.defaults())?;
         }
-        Ok(())
-    }
 
-    fn finalize(self) -> Result<Self::FinalizedPrompt, PromptError> {
-        if self.has_unresolved_vars()? {
-            return Err(PromptError::UnresolvedVars);
+        let variables = self.variables();
+        if !variables.is_empty() {
+            return Err(PromptError::UnresolvedVars(variables));
         }
 
         Ok(ResolvedPromptData(self))
@@ -136,38 +379,51 @@ impl FinalizablePrompt for Prompt {
 impl FinalizablePrompt for PromptList {
     type FinalizedPrompt = ResolvedPromptList;
 
-    fn has_unresolved_vars(&self) -> Result<bool, PromptError> {
-        let re = Regex::new(r"\{\{(?<var>[a-zA-Z_][a-zA-Z0-9_]*)\}\}")?;
-        for (message, _) in self.data.iter() {
-            if re.is_match(message) {
-                return Ok(true);
-            }
+    fn variables(&self) -> Vec<Variable> {
+        self.data
+            .iter()
+            .enumerate()
+            .flat_map(|(index, message)| message.variables(index))
+            .collect()
+    }
+
+    fn format_value(&mut self, values: &Value) -> Result<(), PromptError> {
+        if let Some(schema) = &self.schema {
+            schema.validate(values)?;
         }
 
-        Ok(false)
-    }
+        if self.strict {
+            check_unused(
+                values,
+                self.data
+                    .iter()
+                    .flat_map(|message| message.templates.iter()),
+            )?;
+        }
 
-    fn resolve_var(&mut self, var: &str, value: &str) -> Result<(), PromptError> {
-        let re = Regex::new(&format!(r"\{{\{{(?<var>{})\}}\}}", var))?;
-        let messages = std::mem::take(&mut self.data);
-        for (message, tags) in messages {
-            self.data
-                .push((re.replace_all(&message, value).into(), tags));
+        for message in self.data.iter_mut() {
+            message.resolve(values)?;
         }
 
+        self.record_given(values);
+
         Ok(())
     }
 
-    fn format(&mut self, map: HashMap<&str, &str>) -> Result<(), PromptError> {
-        for (var, value) in map {
-            self.resolve_var(var, value)?;
+    fn finalize(mut self) -> Result<Self::FinalizedPrompt, PromptError> {
+        check_required(self.schema.as_ref(), &self.given, self.variables())?;
+
+        let defaults = self.schema.as_ref().map(Schema::defaults);
+        for message in self.data.iter_mut() {
+            message.resolve_defaults()?;
+            if let Some(defaults) = &defaults {
+                message.resolve(defaults)?;
+            }
         }
-        Ok(())
-    }
 
-    fn finalize(self) -> Result<Self::FinalizedPrompt, PromptError> {
-        if self.has_unresolved_vars()? {
-            return Err(PromptError::UnresolvedVars);
+        let variables = self.variables();
+        if !variables.is_empty() {
+            return Err(PromptError::UnresolvedVars(variables));
         }
 
         Ok(ResolvedPromptData(self))
@@ -175,28 +431,56 @@ impl FinalizablePrompt for PromptList {
 }
 
 impl IntoIterator for PromptList {
-    type Item = (String, Vec<Tag>);
-    type IntoIter = vec::IntoIter<Self::Item>;
+    type Item = (Content, Vec<Tag>);
+    type IntoIter = std::iter::Map<vec::IntoIter<PromptMessage>, fn(PromptMessage) -> Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.data.into_iter()
+        self.data.into_iter().map(Into::into)
     }
 }
 
 impl<'a> Iterator for PromptListIter<'a> {
-    type Item = &'a (String, Vec<Tag>);
+    type Item = &'a (Content, Vec<Tag>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
+        self.iter.next().map(|message| &message.message)
     }
 }
 
 impl IntoIterator for ResolvedPromptList {
-    type Item = (String, Vec<Tag>);
-    type IntoIter = vec::IntoIter<Self::Item>;
+    type Item = (Content, Vec<Tag>);
+    type IntoIter = std::iter::Map<vec::IntoIter<PromptMessage>, fn(PromptMessage) -> Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.data.into_iter()
+        self.0.data.into_iter().map(Into::into)
+    }
+}
+
+impl Serialize for PromptMessage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // The template sources are written out, so unresolved variables and escapes survive.
+        let (content, tags) = &self.message;
+        let mut content = content.clone();
+        for (text, template) in content.texts_mut().zip(self.templates.iter()) {
+            *text = template.source();
+        }
+
+        (content, tags).serialize(serializer)
+    }
+}
+
+impl From<(Content, Tags)> for PromptMessage {
+    fn from((content, tags): (Content, Tags)) -> Self {
+        Self::new(content, tags)
+    }
+}
+
+impl From<PromptMessage> for (Content, Tags) {
+    fn from(message: PromptMessage) -> Self {
+        message.message
     }
 }
 
@@ -208,22 +492,37 @@ impl FinalizedPrompt for ResolvedPrompt {}
 
 impl FinalizedPrompt for ResolvedPromptList {}
 
+impl From<Prompt> for PromptList {
+    fn from(prompt: Prompt) -> Self {
+        let message = PromptMessage {
+            message: (Content::Text(prompt.data.render()), vec![]),
+            templates: vec![prompt.data],
+        };
+
+        Self {
+            data: vec![message],
+            strict: prompt.strict,
+            schema: prompt.schema,
+            given: prompt.given,
+        }
+    }
+}
+
 impl From<ResolvedPromptList> for String {
-    fn from(_: ResolvedPromptList) -> Self {
-        // TODO(nyprothegeek): Combine all the messages into one with roles added.
-        todo!()
+    fn from(prompt: ResolvedPromptList) -> Self {
+        prompt.render_with(&ChatFormat::default())
     }
 }
 
 impl From<Prompt> for String {
     fn from(prompt: Prompt) -> Self {
-        prompt.data
+        prompt.data.render()
     }
 }
 
 impl From<ResolvedPrompt> for String {
     fn from(prompt: ResolvedPrompt) -> Self {
-        prompt.0.data
+        prompt.0.data.render()
     }
 }
 
@@ -234,7 +533,7 @@ impl From<ResolvedPrompt> for String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::map;
+    use crate::{map, prompt, Image, VarSpec, VarType};
     use std::vec;
 
     #[test]
@@ -281,4 +580,238 @@ mod tests {
         let (message, _) = prompt.iter().next().unwrap();
         assert_eq!(message, "Hello ChatGPT! What is your favorite color?");
     }
+
+    #[test]
+    fn test_values_are_substituted_as_is() {
+        let mut prompt = Prompt::new("{{a}} {{b}} \\{{a}}");
+        prompt
+            .format(map!("a" => "{{b}} costs $1", "b" => "${a}"))
+            .unwrap();
+
+        assert_eq!(String::from(prompt), "{{b}} costs $1 ${a} {{a}}");
+
+        let mut prompt = PromptList::new("\\{{literal}} {{name}}", vec![]);
+        prompt.format(map!("name" => "{{literal}}")).unwrap();
+        let prompt = prompt.finalize().unwrap();
+
+        let (message, _) = prompt.iter().next().unwrap();
+        assert_eq!(message, "{{literal}} {{literal}}");
+    }
+
+    #[test]
+    fn test_can_format_structured_values() {
+        let mut prompt = prompt!(
+            system: "Answer using the examples.{{#if context}} Context: {{context | trim}}{{/if}}",
+            user: "{{#each examples}}Q: {{q}}\nA: {{a | upper}}\n{{/each}}{{question}}"
+        );
+        prompt
+            .format_value(&serde_json::json!({
+                "context": "",
+                "examples": [{"q": "1 + 1?", "a": "two"}],
+            }))
+            .unwrap();
+        assert!(prompt.has_unresolved_vars().unwrap());
+
+        let prompt = prompt.resolve(map!("question" => "2 + 2?")).unwrap();
+        let messages: Vec<_> = prompt.iter().map(|(message, _)| message.clone()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                Content::from("Answer using the examples."),
+                Content::from("Q: 1 + 1?\nA: TWO\n2 + 2?"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_can_resolve_from_serializable_context() {
+        #[derive(Serialize)]
+        struct User {
+            name: String,
+            age: u8,
+        }
+
+        #[derive(Serialize)]
+        struct Context {
+            user: User,
+            topics: Vec<&'static str>,
+        }
+
+        let context = Context {
+            user: User {
+                name: "Ada".to_string(),
+                age: 36,
+            },
+            topics: vec!["engines", "poetry"],
+        };
+
+        let prompt = Prompt::new("{{user.name}} ({{user.age}}) likes {{topics[0]}}.")
+            .resolve_with(&context)
+            .unwrap();
+        assert_eq!(String::from(prompt), "Ada (36) likes engines.");
+
+        let result = Prompt::new("{{user.email}}").resolve_with(&context);
+        assert!(matches!(result, Err(PromptError::MissingPath(path)) if path == "user.email"));
+
+        let result = Prompt::new("{{name}}").resolve_with(&["not", "a", "map"]);
+        assert!(matches!(result, Err(PromptError::InvalidContext)));
+    }
+
+    #[test]
+    fn test_unresolved_error_lists_locations() {
+        let mut prompt = prompt!(
+            system: "You help with {{task}}.",
+            user: { "Hi {{name}}, see {{ref}}", Image::url("https://example.com/{{x}}.png"), "{{user.tone}}" }
+        );
+        prompt.format(map!("name" => "Ada")).unwrap();
+
+        let variables = prompt.variables();
+        let locations: Vec<_> = variables
+            .iter()
+            .map(|variable| (variable.path.as_str(), variable.message, variable.part))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![("task", 0, 0), ("ref", 1, 0), ("user.tone", 1, 1)]
+        );
+        assert_eq!(variables[1].span, 12..19);
+
+        let error = prompt.finalize().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Prompt contains unresolved variables: `task` (message 0, part 0, bytes 14..22), \
+             `ref` (message 1, part 0, bytes 12..19), `user.tone` (message 1, part 1, bytes 0..13)"
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unused_variables() {
+        let prompt = Prompt::new("Hello {{name}}! {{#each items}}{{label}}{{/each}}").strict(true);
+        let result = prompt
+            .clone()
+            .resolve(map!("name" => "Ada", "nmae" => "typo", "items" => ""));
+        assert!(matches!(result, Err(PromptError::UnusedVars(unused)) if unused == ["nmae"]));
+
+        let prompt = prompt.resolve_with(&serde_json::json!({
+            "name": "Ada",
+            "items": [{"label": "a"}],
+            "label": "fallback",
+        }));
+        assert_eq!(String::from(prompt.unwrap()), "Hello Ada! a");
+
+        let mut prompt = PromptList::new("{{a}}", vec![]).strict(true);
+        prompt.add_message("{{b}}", vec![]);
+        assert!(prompt.format(map!("a" => "1", "b" => "2")).is_ok());
+        assert!(matches!(
+            prompt.format(map!("a" => "1")),
+            Err(PromptError::UnusedVars(unused)) if unused == ["a"]
+        ));
+    }
+
+    #[test]
+    fn test_finalize_fills_defaults_and_validates() {
+        let schema = Schema::new()
+            .var("question", VarSpec::new(VarType::String).required())
+            .var(
+                "tone",
+                VarSpec::new(VarType::String).default_value("neutral"),
+            )
+            .var("examples", VarSpec::new(VarType::List))
+            .var("name", VarSpec::any());
+
+        let mut prompt = prompt!(
+            system: "Be {{tone}}.{{#each examples}} Example: {{this}}.{{/each}}",
+            user: "{{question}} {{greeting | default: \"Hi\"}} {{name | default: \"you\"}}"
+        )
+        .schema(schema);
+
+        assert!(matches!(
+            prompt.format_with(&serde_json::json!({"examples": "one"})),
+            Err(PromptError::InvalidType { name, expected: VarType::List }) if name == "examples"
+        ));
+
+        let result = prompt.clone().finalize();
+        assert!(matches!(
+            result,
+            Err(PromptError::UnresolvedVars(variables))
+                if variables.len() == 1 && variables[0].name == "question"
+        ));
+
+        prompt.format(map!("question" => "Why?")).unwrap();
+        let prompt = prompt.finalize().unwrap();
+        let messages: Vec<_> = prompt.iter().map(|(message, _)| message.clone()).collect();
+        assert_eq!(
+            messages,
+            vec![Content::from("Be neutral."), Content::from("Why? Hi you")]
+        );
+    }
+
+    #[test]
+    fn test_finalize_requires_required_variables() {
+        let schema = Schema::new()
+            .var("question", VarSpec::new(VarType::String).required())
+            .var("user", VarSpec::new(VarType::Object));
+
+        // A required variable must be given even if the prompt does not refer to it.
+        let result = Prompt::new("Hi {{user.name}}.")
+            .schema(schema.clone())
+            .finalize();
+        assert!(matches!(result, Err(PromptError::MissingRequiredVar(name)) if name == "question"));
+
+        // An inline default does not stand in for a required variable.
+        let result = Prompt::new("{{question | default: \"Why?\"}}")
+            .schema(schema.clone())
+            .finalize();
+        assert!(matches!(
+            result,
+            Err(PromptError::UnresolvedVars(variables)) if variables[0].name == "question"
+        ));
+
+        // A rejected format call does not count as giving its values.
+        let mut prompt = Prompt::new("Hi.").schema(schema.clone());
+        assert!(prompt
+            .format_with(&serde_json::json!({"question": 42}))
+            .is_err());
+        assert!(matches!(
+            prompt.finalize(),
+            Err(PromptError::MissingRequiredVar(name)) if name == "question"
+        ));
+
+        // An optional object without a default is null, and so are the paths into it.
+        let mut prompt = Prompt::new("{{question}} Hi {{user.name}}.").schema(schema);
+        prompt.format(map!("question" => "Why?")).unwrap();
+        assert_eq!(String::from(prompt.finalize().unwrap()), "Why? Hi .");
+    }
+
+    #[test]
+    fn test_serializes_template_sources() {
+        let prompt = PromptList::new("\\{{literal}} {{name}}", vec![]);
+        let (message, _) = prompt.iter().next().unwrap();
+        assert_eq!(message, "{{literal}} {{name}}");
+
+        let json = serde_json::to_string(&prompt).unwrap();
+        let prompt: PromptList = serde_json::from_str(&json).unwrap();
+        let message = prompt.iter().next().unwrap().0.clone();
+        assert_eq!(message, Content::from("{{literal}} {{name}}"));
+        assert!(prompt.has_unresolved_vars().unwrap());
+    }
+
+    #[test]
+    fn test_can_resolve_variables_in_content_parts() {
+        let image = Image::url("https://example.com/{{name}}.png");
+        let mut prompt = PromptList::new(
+            vec!["What is {{name}} doing?".into(), image.clone().into()],
+            vec![Tag::Role(Role::User)],
+        );
+        assert!(prompt.has_unresolved_vars().unwrap());
+
+        prompt.format(map!("name" => "the cat")).unwrap();
+        assert!(!prompt.has_unresolved_vars().unwrap());
+
+        let (message, _) = prompt.iter().next().unwrap();
+        assert_eq!(
+            message,
+            &Content::Parts(vec!["What is the cat doing?".into(), image.into()])
+        );
+    }
 }
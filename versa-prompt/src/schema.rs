@@ -0,0 +1,211 @@
+use crate::PromptError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, fmt};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// Declares the variables of a prompt.
+///
+/// A schema checks the type of the values given when formatting, and fills in defaults when the
+/// prompt is finalized. Required variables must be given before then, and optional variables
+/// without a default resolve to null.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Schema {
+    vars: BTreeMap<String, VarSpec>,
+}
+
+/// The declaration of a single variable.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VarSpec {
+    /// The expected type of the value, any type if not set.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<VarType>,
+
+    /// Whether the variable must be given.
+    #[serde(default)]
+    pub required: bool,
+
+    /// The value used when the variable is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+/// The type of a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VarType {
+    String,
+    Number,
+    Boolean,
+    List,
+    Object,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl Schema {
+    /// Creates an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a variable.
+    pub fn var(mut self, name: impl Into<String>, spec: VarSpec) -> Self {
+        self.vars.insert(name.into(), spec);
+        self
+    }
+
+    /// Returns the declaration of a variable.
+    pub fn get(&self, name: &str) -> Option<&VarSpec> {
+        self.vars.get(name)
+    }
+
+    /// Returns an iterator over the declared variables.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &VarSpec)> {
+        self.vars.iter().map(|(name, spec)| (name.as_str(), spec))
+    }
+
+    /// Checks the given values against the declared types.
+    pub fn validate(&self, values: &Value) -> Result<(), PromptError> {
+        let fields = values.as_object().ok_or(PromptError::InvalidContext)?;
+        for (name, value) in fields {
+            let expected = match self.vars.get(name).and_then(|spec| spec.kind) {
+                Some(expected) => expected,
+                None => continue,
+            };
+
+            if !expected.matches(value) {
+                return Err(PromptError::InvalidType {
+                    name: name.clone(),
+                    expected,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the values of the optional variables: their default, or null if they have none.
+    pub(crate) fn defaults(&self) -> Value {
+        let defaults: Map<_, _> = self
+            .vars
+            .iter()
+            .filter(|(_, spec)| !spec.required)
+            .map(|(name, spec)| (name.clone(), spec.default.clone().unwrap_or(Value::Null)))
+            .collect();
+
+        Value::Object(defaults)
+    }
+}
+
+impl VarSpec {
+    /// Declares an optional variable of the given type.
+    pub fn new(kind: VarType) -> Self {
+        Self {
+            kind: Some(kind),
+            ..Default::default()
+        }
+    }
+
+    /// Declares an optional variable of any type.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// Makes the variable required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Sets the value used when the variable is not given.
+    pub fn default_value(mut self, value: impl Into<Value>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+}
+
+impl VarType {
+    /// Checks if the value has this type. Null matches every type.
+    pub fn matches(&self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (_, Value::Null)
+                | (VarType::String, Value::String(_))
+                | (VarType::Number, Value::Number(_))
+                | (VarType::Boolean, Value::Bool(_))
+                | (VarType::List, Value::Array(_))
+                | (VarType::Object, Value::Object(_))
+        )
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl fmt::Display for VarType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VarType::String => "string",
+            VarType::Number => "number",
+            VarType::Boolean => "boolean",
+            VarType::List => "list",
+            VarType::Object => "object",
+        };
+
+        f.write_str(name)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_can_validate_types() {
+        let schema = Schema::new()
+            .var("question", VarSpec::new(VarType::String).required())
+            .var("examples", VarSpec::new(VarType::List))
+            .var("extra", VarSpec::any());
+
+        assert!(schema
+            .validate(&json!({"question": "Why?", "examples": [], "extra": 1, "other": 2}))
+            .is_ok());
+        assert!(matches!(
+            schema.validate(&json!({"examples": "none"})),
+            Err(PromptError::InvalidType { name, expected: VarType::List }) if name == "examples"
+        ));
+    }
+
+    #[test]
+    fn test_can_deserialize_schema() {
+        let schema: Schema = serde_json::from_value(json!({
+            "tone": {"type": "string", "default": "friendly"},
+            "question": {"type": "string", "required": true},
+        }))
+        .unwrap();
+
+        assert_eq!(
+            schema,
+            Schema::new()
+                .var(
+                    "tone",
+                    VarSpec::new(VarType::String).default_value("friendly")
+                )
+                .var("question", VarSpec::new(VarType::String).required())
+        );
+        assert_eq!(schema.defaults(), json!({"tone": "friendly"}));
+    }
+}
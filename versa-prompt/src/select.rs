@@ -0,0 +1,146 @@
+use crate::PromptError;
+use versa_common::pattern::{self, Pattern};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// One logical prompt with variants tuned for different models.
+///
+/// Variants are keyed by model ids, either exact like `gpt-4` or patterns like `gpt-3.5-*`. An
+/// exact id wins over patterns, patterns are tried in the order they were added, and the fallback
+/// is used when nothing matches.
+#[derive(Debug, Clone)]
+pub struct PromptSelect<P> {
+    variants: Vec<(Pattern, P)>,
+    fallback: Option<P>,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl<P> PromptSelect<P> {
+    /// Creates a selection with no variants.
+    pub fn new() -> Self {
+        Self {
+            variants: vec![],
+            fallback: None,
+        }
+    }
+
+    /// Adds a variant for the models matching the pattern.
+    pub fn variant(mut self, pattern: impl Into<Pattern>, prompt: P) -> Self {
+        self.variants.push((pattern.into(), prompt));
+        self
+    }
+
+    /// Sets the variant used when no pattern matches.
+    pub fn fallback(mut self, prompt: P) -> Self {
+        self.fallback = Some(prompt);
+        self
+    }
+
+    /// Returns the variant for the given model.
+    pub fn select(&self, model: &str) -> Result<&P, PromptError> {
+        self.position(model)
+            .map(|index| &self.variants[index].1)
+            .or(self.fallback.as_ref())
+            .ok_or_else(|| PromptError::NoPromptForModel(model.to_string()))
+    }
+
+    /// Takes the variant for the given model, for resolving it against that model.
+    pub fn into_selected(mut self, model: &str) -> Result<P, PromptError> {
+        match self.position(model) {
+            Some(index) => Ok(self.variants.swap_remove(index).1),
+            None => self
+                .fallback
+                .ok_or_else(|| PromptError::NoPromptForModel(model.to_string())),
+        }
+    }
+
+    /// Returns an iterator over the patterns and their variants.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &P)> {
+        self.variants
+            .iter()
+            .map(|(pattern, prompt)| (pattern.as_str(), prompt))
+    }
+
+    /// Returns the fallback variant.
+    pub fn get_fallback(&self) -> Option<&P> {
+        self.fallback.as_ref()
+    }
+
+    fn position(&self, model: &str) -> Option<usize> {
+        self.variants
+            .iter()
+            .position(|(pattern, _)| pattern == model)
+            .or_else(|| {
+                self.variants
+                    .iter()
+                    .position(|(pattern, _)| pattern::matches(pattern, model))
+            })
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl<P> Default for PromptSelect<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{prompt, select, FinalizablePrompt, Prompt, PromptError};
+
+    fn greeting() -> super::PromptSelect<Prompt> {
+        select! {
+            "gpt-3.5-*" => prompt!("Be brief. Greet {{name}}."),
+            "gpt-4" => prompt!("Greet {{name}}."),
+            "gpt-4*" => prompt!("Greet {{name}} warmly."),
+            _ => prompt!("Say hello to {{name}}."),
+        }
+    }
+
+    #[test]
+    fn test_exact_ids_win_over_patterns() {
+        let select = greeting();
+        let text = |model: &str| String::from(select.select(model).unwrap().clone());
+
+        assert_eq!(text("gpt-4"), "Greet {{name}}.");
+        assert_eq!(text("gpt-4-turbo"), "Greet {{name}} warmly.");
+        assert_eq!(text("gpt-3.5-turbo-16k"), "Be brief. Greet {{name}}.");
+        assert_eq!(text("llama-2-7b"), "Say hello to {{name}}.");
+    }
+
+    #[test]
+    fn test_can_resolve_selected_variant() -> anyhow::Result<()> {
+        let prompt = greeting()
+            .into_selected("gpt-3.5-turbo")?
+            .resolve_with(&serde_json::json!({"name": "Ada"}))?;
+
+        assert_eq!(String::from(prompt), "Be brief. Greet Ada.");
+        Ok(())
+    }
+
+    #[test]
+    fn test_fails_without_fallback() {
+        let select = select! {
+            "claude-?" => prompt!(system: "Answer in French.", user: "{{question}}"),
+        };
+
+        assert!(select.select("claude-2").is_ok());
+        assert!(matches!(
+            select.select("claude-20"),
+            Err(PromptError::NoPromptForModel(model)) if model == "claude-20"
+        ));
+    }
+}
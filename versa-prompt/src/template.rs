@@ -0,0 +1,1409 @@
+//! Parsed prompt templates.
+//!
+//! Templates are parsed once into a tree of nodes when a prompt is created, so resolving variables
+//! is a single pass over the nodes instead of a scan of the whole text per variable.
+//!
+//! Besides `{{name}}` placeholders, templates support:
+//!
+//! - Paths into structured values: `{{user.name}}`, `{{items[0]}}` or `{{items.0.title}}`.
+//! - Filters, applied left to right: `{{name | trim | truncate: 200}}`.
+//! - Conditionals: `{{#if context}}Context: {{context}}{{else}}No context.{{/if}}`.
+//! - Loops over lists: `{{#each examples}}- {{this}}{{/each}}`. Inside the loop, `this` is the
+//!   current item and the fields of object items can be used directly.
+//!
+//! The available filters are `upper`, `lower`, `trim`, `truncate: N` (characters),
+//! `truncate_tokens: N` (whitespace-separated tokens), `json_escape`, `indent: N` and
+//! `default: VALUE`. A variable with a `default` filter that is still missing when the prompt is
+//! finalized takes the default value, as does a null value or a missing path.
+
+use crate::PromptError;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::{collections::BTreeSet, fmt, mem, ops::Range};
+
+//-------------------------------------------------------------------------------------------------
+// Types
+//-------------------------------------------------------------------------------------------------
+
+/// A template, parsed into literal text, placeholders and blocks.
+///
+/// Values substituted into a template are opaque text: they are never parsed for placeholders or
+/// escapes, so a value containing `{{name}}` or `$1` ends up in the output as is.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Var(Expr),
+    If {
+        condition: Expr,
+        then: Vec<Node>,
+        otherwise: Option<Vec<Node>>,
+    },
+    Each {
+        list: Expr,
+        body: Vec<Node>,
+    },
+}
+
+/// An unresolved placeholder of a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    /// The name of the variable.
+    pub name: String,
+
+    /// The full path of the placeholder, such as `user.name`.
+    pub path: String,
+
+    /// The index of the message in a `PromptList`, always zero for a `Prompt`.
+    pub message: usize,
+
+    /// The index of the text part within the message, skipping images.
+    pub part: usize,
+
+    /// The byte range of the placeholder tag in the text rendered so far, which is what the
+    /// message shows.
+    pub span: Range<usize>,
+}
+
+/// A variable, a path into its value, and filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expr {
+    name: String,
+    path: Vec<Segment>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Filter {
+    name: String,
+    arg: Option<Value>,
+}
+
+/// A tag in the template source.
+enum Tag {
+    Var(Expr),
+    If(Expr),
+    Each(Expr),
+    Else,
+    EndIf,
+    EndEach,
+}
+
+/// A block that has been opened but not closed yet while parsing.
+struct OpenBlock {
+    tag: Tag,
+    source: String,
+    then: Option<Template>,
+    nodes: Template,
+}
+
+/// The variables visible while resolving.
+struct Scope<'a> {
+    value: &'a Value,
+    is_item: bool,
+    defaults: bool,
+    parent: Option<&'a Scope<'a>>,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Methods
+//-------------------------------------------------------------------------------------------------
+
+impl Template {
+    /// Parses the template source.
+    ///
+    /// Variable names are made of ASCII letters, digits and underscores, not starting with a digit,
+    /// and must directly follow the opening braces. A backslash before `{{` makes the braces
+    /// literal, and a doubled backslash before `{{` stands for a literal backslash. Anything that is
+    /// not a valid tag, including blocks that are never closed, is kept as literal text.
+    pub fn parse(source: &str) -> Self {
+        let mut root = Self::default();
+        let mut blocks: Vec<OpenBlock> = vec![];
+        let mut rest = source;
+
+        while let Some(start) = rest.find(['\\', '{']) {
+            current(&mut root, &mut blocks).push_text(&rest[..start]);
+            rest = &rest[start..];
+
+            let backslashes = rest.len() - rest.trim_start_matches('\\').len();
+            if backslashes > 0 {
+                let after = &rest[backslashes..];
+                let current = current(&mut root, &mut blocks);
+                if !after.starts_with("{{") {
+                    current.push_text(&rest[..backslashes]);
+                    rest = after;
+                    continue;
+                }
+
+                current.push_text(&rest[..backslashes / 2]);
+                if backslashes % 2 == 1 {
+                    current.push_text("{{");
+                    rest = &after[2..];
+                } else {
+                    rest = after;
+                }
+                continue;
+            }
+
+            let (tag, len) = match parse_tag(rest) {
+                Some(tag) => tag,
+                None => {
+                    // The next brace may open a tag itself, as in `{{{name}}`.
+                    current(&mut root, &mut blocks).push_text("{");
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+
+            let tag_source = &rest[..len];
+            rest = &rest[len..];
+            match tag {
+                Tag::Var(expr) => current(&mut root, &mut blocks).push_node(Node::Var(expr)),
+                tag @ (Tag::If(_) | Tag::Each(_)) => blocks.push(OpenBlock {
+                    tag,
+                    source: tag_source.to_string(),
+                    then: None,
+                    nodes: Self::default(),
+                }),
+                Tag::Else => match blocks.last_mut() {
+                    Some(block) if matches!(block.tag, Tag::If(_)) && block.then.is_none() => {
+                        block.then = Some(mem::take(&mut block.nodes));
+                    }
+                    _ => current(&mut root, &mut blocks).push_text(tag_source),
+                },
+                Tag::EndIf | Tag::EndEach => {
+                    let closes = matches!(
+                        (blocks.last().map(|block| &block.tag), &tag),
+                        (Some(Tag::If(_)), Tag::EndIf) | (Some(Tag::Each(_)), Tag::EndEach)
+                    );
+
+                    match blocks.pop() {
+                        Some(block) if closes => {
+                            current(&mut root, &mut blocks).push_node(block.close())
+                        }
+                        block => {
+                            blocks.extend(block);
+                            current(&mut root, &mut blocks).push_text(tag_source);
+                        }
+                    }
+                }
+            }
+        }
+
+        current(&mut root, &mut blocks).push_text(rest);
+
+        // Blocks that are never closed are kept as literal text.
+        while let Some(block) = blocks.pop() {
+            let parent = current(&mut root, &mut blocks);
+            parent.push_text(&block.source);
+            if let Some(then) = block.then {
+                parent.extend(then.nodes);
+                parent.push_text("{{else}}");
+            }
+            parent.extend(block.nodes.nodes);
+        }
+
+        root
+    }
+
+    /// Returns the template source, escaping literal braces so that parsing it gives back this
+    /// template.
+    pub fn source(&self) -> String {
+        let mut source = String::new();
+        write_source(&self.nodes, false, &mut source);
+        source
+    }
+
+    /// Returns the unresolved placeholders, in order of appearance, with `message` and `part` set
+    /// to zero.
+    ///
+    /// Variables used inside loops that have not been expanded are not included, as they may refer
+    /// to fields of the list items.
+    pub fn variables(&self) -> Vec<Variable> {
+        let mut variables = vec![];
+        collect_variables(&self.nodes, 0, &mut variables);
+        variables
+            .into_iter()
+            .map(|(expr, span)| Variable {
+                name: expr.name.clone(),
+                path: expr.path(),
+                message: 0,
+                part: 0,
+                span,
+            })
+            .collect()
+    }
+
+    /// Returns the names of every variable the template refers to, including inside loops.
+    pub(crate) fn referenced_names(&self) -> BTreeSet<&str> {
+        let mut names = BTreeSet::new();
+        collect_names(&self.nodes, &mut names);
+        names
+    }
+
+    /// Checks that every filter is known and has a valid argument, including inside loops, so
+    /// mistakes surface before any value is given.
+    pub fn validate(&self) -> Result<(), PromptError> {
+        validate_nodes(&self.nodes)
+    }
+
+    /// Checks if the template still has unresolved variables or blocks.
+    pub fn has_variables(&self) -> bool {
+        self.nodes.iter().any(|node| !matches!(node, Node::Text(_)))
+    }
+
+    /// Resolves the variables and blocks whose values are found in `values`, in a single pass.
+    ///
+    /// `values` must be a JSON object. Anything that refers to a missing variable is kept as is, so
+    /// it can be resolved later.
+    pub fn resolve(&mut self, values: &Value) -> Result<(), PromptError> {
+        self.resolve_scope(values, false)
+    }
+
+    /// Resolves the variables that are still missing but have a `default` filter.
+    pub fn resolve_defaults(&mut self) -> Result<(), PromptError> {
+        self.resolve_scope(&Value::Object(Default::default()), true)
+    }
+
+    fn resolve_scope(&mut self, values: &Value, defaults: bool) -> Result<(), PromptError> {
+        if !values.is_object() {
+            return Err(PromptError::InvalidContext);
+        }
+
+        if !self.has_variables() {
+            return Ok(());
+        }
+
+        let scope = Scope {
+            value: values,
+            is_item: false,
+            defaults,
+            parent: None,
+        };
+
+        let mut resolved = Self::default();
+        resolved.resolve_nodes(mem::take(&mut self.nodes), &scope)?;
+        *self = resolved;
+        Ok(())
+    }
+
+    /// Renders the template, writing unresolved variables back as placeholders and literal braces
+    /// unescaped.
+    pub fn render(&self) -> String {
+        self.to_string()
+    }
+
+    fn resolve_nodes(&mut self, nodes: Vec<Node>, scope: &Scope) -> Result<(), PromptError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => self.push_text(&text),
+                Node::Var(expr) => match expr.evaluate(scope)? {
+                    Some(value) => self.push_text(&to_text(&value)),
+                    None => self.nodes.push(Node::Var(expr)),
+                },
+                Node::If {
+                    condition,
+                    then,
+                    otherwise,
+                } => match condition.evaluate(scope)? {
+                    Some(value) if is_truthy(&value) => self.resolve_nodes(then, scope)?,
+                    Some(_) => self.resolve_nodes(otherwise.unwrap_or_default(), scope)?,
+                    None => {
+                        let mut resolved_then = Self::default();
+                        resolved_then.resolve_nodes(then, scope)?;
+                        let otherwise = match otherwise {
+                            Some(otherwise) => {
+                                let mut resolved = Self::default();
+                                resolved.resolve_nodes(otherwise, scope)?;
+                                Some(resolved.nodes)
+                            }
+                            None => None,
+                        };
+
+                        self.nodes.push(Node::If {
+                            condition,
+                            then: resolved_then.nodes,
+                            otherwise,
+                        });
+                    }
+                },
+                Node::Each { list, body } => match list.evaluate(scope)? {
+                    Some(Value::Array(items)) => {
+                        for item in items.iter() {
+                            let scope = Scope {
+                                value: item,
+                                is_item: true,
+                                defaults: scope.defaults,
+                                parent: Some(scope),
+                            };
+                            self.resolve_nodes(body.clone(), &scope)?;
+                        }
+                    }
+                    Some(Value::Null) => {}
+                    Some(_) => return Err(PromptError::NotAList(list.path())),
+                    None => self.nodes.push(Node::Each { list, body }),
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        match self.nodes.last_mut() {
+            Some(Node::Text(last)) => last.push_str(text),
+            _ => self.nodes.push(Node::Text(text.to_string())),
+        }
+    }
+
+    fn push_node(&mut self, node: Node) {
+        match node {
+            Node::Text(text) => self.push_text(&text),
+            node => self.nodes.push(node),
+        }
+    }
+
+    fn extend(&mut self, nodes: Vec<Node>) {
+        for node in nodes {
+            self.push_node(node);
+        }
+    }
+}
+
+impl OpenBlock {
+    fn close(self) -> Node {
+        match self.tag {
+            Tag::If(condition) => match self.then {
+                Some(then) => Node::If {
+                    condition,
+                    then: then.nodes,
+                    otherwise: Some(self.nodes.nodes),
+                },
+                None => Node::If {
+                    condition,
+                    then: self.nodes.nodes,
+                    otherwise: None,
+                },
+            },
+            Tag::Each(list) => Node::Each {
+                list,
+                body: self.nodes.nodes,
+            },
+            _ => unreachable!("only `if` and `each` open blocks"),
+        }
+    }
+}
+
+impl Expr {
+    /// Looks the variable up and applies the filters, or returns `None` if it is not in scope.
+    ///
+    /// A variable that is in scope but lacks a field or item on the path is an error, unless it
+    /// has a default. A path into null is null.
+    fn evaluate(&self, scope: &Scope) -> Result<Option<Value>, PromptError> {
+        let mut value = match scope.get(&self.name) {
+            Some(value) => self.lookup(value)?,
+            None if scope.defaults && self.has_default() => Value::Null,
+            None => return Ok(None),
+        };
+
+        for filter in self.filters.iter() {
+            value = filter.apply(value)?;
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Follows the path into the value of the variable.
+    fn lookup(&self, mut value: &Value) -> Result<Value, PromptError> {
+        for segment in self.path.iter() {
+            let next = match (segment, value) {
+                // A missing optional value stays missing all the way down.
+                (_, Value::Null) => return Ok(Value::Null),
+                (Segment::Key(key), Value::Object(fields)) => fields.get(key),
+                (Segment::Index(index), Value::Array(items)) => items.get(*index),
+                (Segment::Index(index), Value::Object(fields)) => fields.get(&index.to_string()),
+                _ => None,
+            };
+
+            value = match next {
+                Some(next) => next,
+                None if self.has_default() => return Ok(Value::Null),
+                None => return Err(PromptError::MissingPath(self.path())),
+            };
+        }
+
+        Ok(value.clone())
+    }
+
+    fn has_default(&self) -> bool {
+        self.filters.iter().any(|filter| filter.name == "default")
+    }
+
+    /// Returns the variable and its path, without filters.
+    fn path(&self) -> String {
+        let mut path = self.name.clone();
+        for segment in self.path.iter() {
+            match segment {
+                Segment::Key(key) => {
+                    path.push('.');
+                    path.push_str(key);
+                }
+                Segment::Index(index) => path.push_str(&format!("[{index}]")),
+            }
+        }
+
+        path
+    }
+}
+
+impl Filter {
+    fn apply(&self, value: Value) -> Result<Value, PromptError> {
+        if self.name == "default" {
+            let default = self
+                .arg
+                .as_ref()
+                .ok_or_else(|| PromptError::InvalidFilterArgument(self.name.clone()))?;
+
+            return Ok(match value {
+                Value::Null => default.clone(),
+                value => value,
+            });
+        }
+
+        let text = to_text(&value);
+        let text = match self.name.as_str() {
+            "upper" => text.to_uppercase(),
+            "lower" => text.to_lowercase(),
+            "trim" => text.trim().to_string(),
+            "truncate" => text.chars().take(self.count()?).collect(),
+            "truncate_tokens" => truncate_tokens(&text, self.count()?).to_string(),
+            "json_escape" => {
+                let quoted = Value::String(text).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+            "indent" => indent(&text, self.count()?),
+            _ => return Err(PromptError::UnknownFilter(self.name.clone())),
+        };
+
+        Ok(Value::String(text))
+    }
+
+    fn count(&self) -> Result<usize, PromptError> {
+        self.arg
+            .as_ref()
+            .and_then(Value::as_u64)
+            .map(|count| count as usize)
+            .ok_or_else(|| PromptError::InvalidFilterArgument(self.name.clone()))
+    }
+}
+
+impl<'a> Scope<'a> {
+    fn get(&self, name: &str) -> Option<&'a Value> {
+        if name == "this" && self.is_item {
+            return Some(self.value);
+        }
+
+        self.value
+            .as_object()
+            .and_then(|object| object.get(name))
+            .or_else(|| self.parent.and_then(|parent| parent.get(name)))
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Functions
+//-------------------------------------------------------------------------------------------------
+
+/// Returns the template new nodes are added to while parsing.
+fn current<'a>(root: &'a mut Template, blocks: &'a mut [OpenBlock]) -> &'a mut Template {
+    match blocks.last_mut() {
+        Some(block) => &mut block.nodes,
+        None => root,
+    }
+}
+
+/// Parses the tag at the start of `text`, returning it with its length, braces included.
+fn parse_tag(text: &str) -> Option<(Tag, usize)> {
+    let inner = text.strip_prefix("{{")?;
+    for (keyword, tag) in [
+        ("else}}", Tag::Else),
+        ("/if}}", Tag::EndIf),
+        ("/each}}", Tag::EndEach),
+    ] {
+        if inner.starts_with(keyword) {
+            return Some((tag, keyword.len() + 2));
+        }
+    }
+
+    let (tag, rest) = if let Some(rest) = inner.strip_prefix("#if ") {
+        let (expr, rest) = parse_expr(rest.trim_start())?;
+        (Tag::If(expr), rest)
+    } else if let Some(rest) = inner.strip_prefix("#each ") {
+        let (expr, rest) = parse_expr(rest.trim_start())?;
+        (Tag::Each(expr), rest)
+    } else {
+        let (expr, rest) = parse_expr(inner)?;
+        (Tag::Var(expr), rest)
+    };
+
+    rest.starts_with("}}")
+        .then(|| (tag, text.len() - rest.len() + 2))
+}
+
+/// Parses a variable, its path and its filters, returning the rest of the text.
+fn parse_expr(text: &str) -> Option<(Expr, &str)> {
+    let (name, mut rest) = parse_name(text)?;
+    let mut path = vec![];
+    loop {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let (segment, after_segment) = match parse_index(after_dot) {
+                Some((index, after_index)) => (Segment::Index(index), after_index),
+                None => {
+                    let (key, after_key) = parse_name(after_dot)?;
+                    (Segment::Key(key.to_string()), after_key)
+                }
+            };
+            path.push(segment);
+            rest = after_segment;
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let (index, after_index) = parse_index(after_bracket)?;
+            path.push(Segment::Index(index));
+            rest = after_index.strip_prefix(']')?;
+        } else {
+            break;
+        }
+    }
+
+    let mut filters = vec![];
+    while let Some(after_pipe) = rest.trim_start().strip_prefix('|') {
+        let (name, after_name) = parse_name(after_pipe.trim_start())?;
+        let (arg, after_arg) = match after_name.trim_start().strip_prefix(':') {
+            Some(after_colon) => {
+                let (arg, after_arg) = parse_arg(after_colon.trim_start())?;
+                (Some(arg), after_arg)
+            }
+            None => (None, after_name),
+        };
+
+        filters.push(Filter {
+            name: name.to_string(),
+            arg,
+        });
+        rest = after_arg;
+    }
+
+    let expr = Expr {
+        name: name.to_string(),
+        path,
+        filters,
+    };
+
+    Some((expr, rest.trim_start()))
+}
+
+fn parse_name(text: &str) -> Option<(&str, &str)> {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return None;
+    }
+
+    let len = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+
+    Some(text.split_at(len))
+}
+
+fn parse_index(text: &str) -> Option<(usize, &str)> {
+    let len = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let index = text[..len].parse().ok()?;
+    Some((index, &text[len..]))
+}
+
+/// Parses a filter argument, which is an unsigned integer, a boolean or a JSON string.
+fn parse_arg(text: &str) -> Option<(Value, &str)> {
+    if let Some((word, rest)) = parse_name(text) {
+        let arg = match word {
+            "true" => true,
+            "false" => false,
+            _ => return None,
+        };
+
+        return Some((Value::Bool(arg), rest));
+    }
+
+    if let Some(quoted) = text.strip_prefix('"') {
+        let mut escaped = false;
+        let end = quoted.find(|c: char| {
+            let end = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            end
+        })? + 2;
+
+        let arg = serde_json::from_str(&text[..end]).ok()?;
+        return Some((arg, &text[end..]));
+    }
+
+    let len = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let arg = text[..len].parse::<u64>().ok()?;
+    Some((Value::from(arg), &text[len..]))
+}
+
+/// Writes the nodes as template source.
+///
+/// `closed` tells if a tag follows the nodes, as is the case inside blocks.
+fn write_source(nodes: &[Node], closed: bool, source: &mut String) {
+    for (i, node) in nodes.iter().enumerate() {
+        match node {
+            Node::Text(text) => {
+                let before_tag = closed || i + 1 < nodes.len();
+                escape_text(text, before_tag, source);
+            }
+            Node::Var(expr) => source.push_str(&format!("{{{{{expr}}}}}")),
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                source.push_str(&format!("{{{{#if {condition}}}}}"));
+                write_source(then, true, source);
+                if let Some(otherwise) = otherwise {
+                    source.push_str("{{else}}");
+                    write_source(otherwise, true, source);
+                }
+                source.push_str("{{/if}}");
+            }
+            Node::Each { list, body } => {
+                source.push_str(&format!("{{{{#each {list}}}}}"));
+                write_source(body, true, source);
+                source.push_str("{{/each}}");
+            }
+        }
+    }
+}
+
+/// Writes literal text so that it parses back as the same text.
+///
+/// `before_tag` tells if a tag follows the text, which makes trailing backslashes and a trailing
+/// brace significant.
+fn escape_text(text: &str, before_tag: bool, source: &mut String) {
+    let mut rest = text;
+    while let Some(start) = rest.find(['\\', '{']) {
+        source.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let backslashes = rest.len() - rest.trim_start_matches('\\').len();
+        if backslashes > 0 {
+            // Backslashes only escape when a `{{` follows them in the source.
+            let after = &rest[backslashes..];
+            let escapes =
+                after.starts_with("{{") || (before_tag && (after.is_empty() || after == "{"));
+            source.push_str(&rest[..backslashes]);
+            if escapes {
+                source.push_str(&rest[..backslashes]);
+            }
+            rest = after;
+        } else if parse_tag(rest).is_some() {
+            source.push_str("\\{{");
+            rest = &rest[2..];
+        } else {
+            source.push('{');
+            rest = &rest[1..];
+        }
+    }
+
+    source.push_str(rest);
+}
+
+/// Collects the placeholders with their byte range in the rendered text, which starts at `offset`.
+/// Returns the offset after the nodes.
+fn collect_variables<'a>(
+    nodes: &'a [Node],
+    mut offset: usize,
+    variables: &mut Vec<(&'a Expr, Range<usize>)>,
+) -> usize {
+    for node in nodes {
+        match node {
+            Node::Text(text) => offset += text.len(),
+            Node::Var(expr) => {
+                offset = push_tag(variables, expr, format!("{{{{{expr}}}}}"), offset)
+            }
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                offset = push_tag(
+                    variables,
+                    condition,
+                    format!("{{{{#if {condition}}}}}"),
+                    offset,
+                );
+                offset = collect_variables(then, offset, variables);
+                if let Some(otherwise) = otherwise {
+                    offset = collect_variables(otherwise, offset + "{{else}}".len(), variables);
+                }
+                offset += "{{/if}}".len();
+            }
+            Node::Each { list, body } => {
+                offset = push_tag(variables, list, format!("{{{{#each {list}}}}}"), offset);
+                // The body may refer to fields of the list items, so only its length counts.
+                offset = collect_variables(body, offset, &mut vec![]);
+                offset += "{{/each}}".len();
+            }
+        }
+    }
+
+    offset
+}
+
+fn push_tag<'a>(
+    variables: &mut Vec<(&'a Expr, Range<usize>)>,
+    expr: &'a Expr,
+    tag: String,
+    offset: usize,
+) -> usize {
+    variables.push((expr, offset..offset + tag.len()));
+    offset + tag.len()
+}
+
+fn collect_names<'a>(nodes: &'a [Node], names: &mut BTreeSet<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var(expr) => {
+                names.insert(&expr.name);
+            }
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                names.insert(&condition.name);
+                collect_names(then, names);
+                if let Some(otherwise) = otherwise {
+                    collect_names(otherwise, names);
+                }
+            }
+            Node::Each { list, body } => {
+                names.insert(&list.name);
+                collect_names(body, names);
+            }
+        }
+    }
+}
+
+fn validate_nodes(nodes: &[Node]) -> Result<(), PromptError> {
+    let validate_expr = |expr: &Expr| {
+        expr.filters
+            .iter()
+            .try_for_each(|filter| filter.apply(Value::Null).map(drop))
+    };
+
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var(expr) => validate_expr(expr)?,
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                validate_expr(condition)?;
+                validate_nodes(then)?;
+                if let Some(otherwise) = otherwise {
+                    validate_nodes(otherwise)?;
+                }
+            }
+            Node::Each { list, body } => {
+                validate_expr(list)?;
+                validate_nodes(body)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn fmt_nodes(nodes: &[Node], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for node in nodes {
+        match node {
+            Node::Text(text) => f.write_str(text)?,
+            Node::Var(expr) => write!(f, "{{{{{expr}}}}}")?,
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                write!(f, "{{{{#if {condition}}}}}")?;
+                fmt_nodes(then, f)?;
+                if let Some(otherwise) = otherwise {
+                    f.write_str("{{else}}")?;
+                    fmt_nodes(otherwise, f)?;
+                }
+                f.write_str("{{/if}}")?;
+            }
+            Node::Each { list, body } => {
+                write!(f, "{{{{#each {list}}}}}")?;
+                fmt_nodes(body, f)?;
+                f.write_str("{{/each}}")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the text a value is rendered as.
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+    }
+}
+
+/// Keeps the first `count` whitespace-separated tokens of the text.
+fn truncate_tokens(text: &str, count: usize) -> &str {
+    let mut tokens = 0;
+    let mut in_token = false;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            in_token = false;
+        } else if !in_token {
+            if tokens == count {
+                return text[..i].trim_end();
+            }
+            tokens += 1;
+            in_token = true;
+        }
+    }
+
+    text
+}
+
+/// Indents every line but the first, which is already placed by the template.
+fn indent(text: &str, width: usize) -> String {
+    let prefix = " ".repeat(width);
+    let mut lines = text.split('\n');
+    let mut indented = lines.next().unwrap_or_default().to_string();
+    for line in lines {
+        indented.push('\n');
+        if !line.is_empty() {
+            indented.push_str(&prefix);
+        }
+        indented.push_str(line);
+    }
+
+    indented
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trait Implementations
+//-------------------------------------------------------------------------------------------------
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_nodes(&self.nodes, f)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.path())?;
+        for filter in self.filters.iter() {
+            write!(f, " | {}", filter.name)?;
+            if let Some(arg) = &filter.arg {
+                write!(f, ": {arg}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` (message {}, part {}, bytes {}..{})",
+            self.path, self.message, self.part, self.span.start, self.span.end
+        )
+    }
+}
+
+impl From<String> for Template {
+    fn from(source: String) -> Self {
+        Self::parse(&source)
+    }
+}
+
+impl From<&str> for Template {
+    fn from(source: &str) -> Self {
+        Self::parse(source)
+    }
+}
+
+impl From<Template> for String {
+    fn from(template: Template) -> Self {
+        template.render()
+    }
+}
+
+impl Serialize for Template {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.source())
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn names(template: &Template) -> Vec<String> {
+        template
+            .variables()
+            .into_iter()
+            .map(|variable| variable.name)
+            .collect()
+    }
+
+    fn render(source: &str, values: Value) -> String {
+        let mut template = Template::parse(source);
+        template.resolve(&values).unwrap();
+        template.render()
+    }
+
+    #[test]
+    fn test_can_parse_templates() {
+        let template = Template::parse("Hello {{name}}, {{ not_a_var }} {{1st}} {{name!");
+        assert_eq!(names(&template), vec!["name"]);
+        assert_eq!(
+            template.render(),
+            "Hello {{name}}, {{ not_a_var }} {{1st}} {{name!"
+        );
+
+        let template = Template::parse("{{{a}}}} {{elsewhere}}");
+        assert_eq!(names(&template), vec!["a", "elsewhere"]);
+        assert_eq!(template.render(), "{{{a}}}} {{elsewhere}}");
+    }
+
+    #[test]
+    fn test_resolves_in_a_single_pass() {
+        let mut template = Template::parse("{{a}} and {{b}} and {{a}}");
+        template.resolve(&json!({"a": "{{b}}"})).unwrap();
+        assert_eq!(template.render(), "{{b}} and {{b}} and {{b}}");
+        assert_eq!(names(&template), vec!["b"]);
+
+        template.resolve(&json!({"b": "B"})).unwrap();
+        assert_eq!(template.render(), "{{b}} and B and {{b}}");
+        assert!(!template.has_variables());
+    }
+
+    #[test]
+    fn test_can_escape_braces() {
+        let template = Template::parse(r"\{{name}} \\{{name}} \\\{{name}} \x {{ name }}");
+        assert_eq!(names(&template), vec!["name"]);
+        assert_eq!(
+            template.render(),
+            r"{{name}} \{{name}} \{{name}} \x {{ name }}"
+        );
+        assert_eq!(Template::parse(&template.source()), template);
+
+        let mut template = Template::parse(r"\{{a}}{{a}}");
+        template.resolve(&json!({"a": "$1 {{a}}"})).unwrap();
+        assert_eq!(template.render(), "{{a}}$1 {{a}}");
+        assert_eq!(template.source(), r"\{{a}}$1 \{{a}}");
+    }
+
+    #[test]
+    fn test_can_use_conditionals() {
+        let source = "{{#if context}}Context: {{context}}\n{{else}}No context.\n{{/if}}Answer.";
+        assert_eq!(
+            render(source, json!({"context": "docs"})),
+            "Context: docs\nAnswer."
+        );
+        assert_eq!(
+            render(source, json!({"context": ""})),
+            "No context.\nAnswer."
+        );
+
+        let mut template = Template::parse(source);
+        template.resolve(&json!({"other": 1})).unwrap();
+        assert_eq!(template.render(), source);
+        assert_eq!(names(&template), vec!["context"; 2]);
+    }
+
+    #[test]
+    fn test_can_loop_over_lists() {
+        let source =
+            "{{#each examples}}- {{input}} => {{output}} ({{this | json_escape}}){{/each}}";
+        let output = render(
+            source,
+            json!({"examples": [{"input": "1 + 1", "output": 2}], "output": "unused"}),
+        );
+        assert_eq!(
+            output,
+            r#"- 1 + 1 => 2 ({\"input\":\"1 + 1\",\"output\":2})"#
+        );
+
+        let source = "{{#each items}}{{this}}{{#if sep}}{{sep}}{{/if}}{{/each}}";
+        assert_eq!(
+            render(source, json!({"items": ["a", "b"], "sep": ","})),
+            "a,b,"
+        );
+
+        let mut template = Template::parse(source);
+        assert!(matches!(
+            template.resolve(&json!({"items": "a"})),
+            Err(PromptError::NotAList(name)) if name == "items"
+        ));
+    }
+
+    #[test]
+    fn test_can_apply_filters() {
+        let values = json!({"text": "  Hello wide\n world  ", "n": 42});
+        assert_eq!(
+            render("{{text | trim | upper}}", values.clone()),
+            "HELLO WIDE\n WORLD"
+        );
+        assert_eq!(render("{{text|trim|truncate:5}}", values.clone()), "Hello");
+        assert_eq!(
+            render("{{text | truncate_tokens: 2}}", values.clone()),
+            "  Hello wide"
+        );
+        assert_eq!(
+            render("{{text | trim | indent: 2}}", values.clone()),
+            "Hello wide\n   world"
+        );
+        assert_eq!(render("{{n | lower}}", values.clone()), "42");
+
+        let mut template = Template::parse("{{text | shout}}");
+        assert!(matches!(
+            template.resolve(&values),
+            Err(PromptError::UnknownFilter(name)) if name == "shout"
+        ));
+
+        let mut template = Template::parse("{{text | truncate}}");
+        assert!(matches!(
+            template.resolve(&values),
+            Err(PromptError::InvalidFilterArgument(name)) if name == "truncate"
+        ));
+
+        let template = Template::parse("{{#each items}}{{this | shout}}{{/each}}");
+        assert!(matches!(
+            template.validate(),
+            Err(PromptError::UnknownFilter(name)) if name == "shout"
+        ));
+        assert!(Template::parse("{{text | default: \"none\" | indent: 2}}")
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_can_use_paths() {
+        let values = json!({
+            "user": {"name": "Ada", "langs": ["en", "fr"]},
+            "posts": [{"title": "Notes", "tags": {"0": "math"}}],
+        });
+        assert_eq!(
+            render(
+                "{{user.name}} speaks {{user.langs[1]}} and wrote {{posts.0.title}} on {{posts[0].tags.0}}",
+                values.clone(),
+            ),
+            "Ada speaks fr and wrote Notes on math"
+        );
+        assert_eq!(
+            render(
+                "{{#each posts}}{{this.title | upper}} by {{user.name}}{{/each}}",
+                values.clone()
+            ),
+            "NOTES by Ada"
+        );
+
+        let mut template = Template::parse("{{user.email}} {{missing.path}}");
+        assert!(matches!(
+            template.resolve(&values),
+            Err(PromptError::MissingPath(path)) if path == "user.email"
+        ));
+
+        let mut template = Template::parse("{{user.langs.2}}");
+        assert!(matches!(
+            template.resolve(&values),
+            Err(PromptError::MissingPath(path)) if path == "user.langs[2]"
+        ));
+
+        let mut template = Template::parse("{{missing.path}}");
+        template.resolve(&values).unwrap();
+        assert_eq!(template.render(), "{{missing.path}}");
+        assert_eq!(names(&template), vec!["missing"]);
+    }
+
+    #[test]
+    fn test_variables_have_spans() {
+        let source = "Hi {{user.name | upper}}!{{#if x}}{{#each items}}{{y}}{{/each}}{{/if}}";
+        let variables = Template::parse(source).variables();
+        let spans: Vec<_> = variables
+            .iter()
+            .map(|variable| (variable.path.as_str(), &source[variable.span.clone()]))
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                ("user.name", "{{user.name | upper}}"),
+                ("x", "{{#if x}}"),
+                ("items", "{{#each items}}"),
+            ]
+        );
+
+        // Spans follow the text as it is rendered after resolving.
+        let mut template = Template::parse(source);
+        template.resolve(&json!({"x": true})).unwrap();
+        let rendered = template.render();
+        let span = template.variables()[1].span.clone();
+        assert_eq!(&rendered[span], "{{#each items}}");
+
+        assert_eq!(
+            template.referenced_names().into_iter().collect::<Vec<_>>(),
+            vec!["items", "user", "y"]
+        );
+
+        let mut template = Template::parse("Hi {{name}}, see {{ref}}");
+        template.resolve(&json!({"name": "Ada"})).unwrap();
+        assert_eq!(template.variables()[0].span, 12..19);
+    }
+
+    #[test]
+    fn test_parses_the_shared_template_cases() {
+        // The same cases are checked against the `PromptTemplate` derive in inner-macros.
+        for line in include_str!("template_cases.txt").lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut columns = line.split('\t');
+            let source = columns.next().unwrap().replace("\\n", "\n");
+            let required: BTreeSet<_> = columns
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect();
+            let used: BTreeSet<_> = columns
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect();
+
+            let mut template = Template::parse(&source);
+            assert_eq!(template.referenced_names(), used, "{source}");
+
+            template.resolve_defaults().unwrap();
+            let variables = template.variables();
+            let names: BTreeSet<_> = variables
+                .iter()
+                .map(|variable| variable.name.as_str())
+                .collect();
+            assert_eq!(names, required, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_can_use_inline_defaults() {
+        let source = r#"Be {{tone | default: "friendly" | upper}}.{{#if brief | default: true}} Be brief.{{/if}} {{user.name | default: "there"}}"#;
+        let mut template = Template::parse(source);
+        template.resolve(&json!({"user": {}})).unwrap();
+        assert_eq!(
+            names(&template),
+            vec!["tone", "brief"],
+            "defaults only apply to missing variables when finalizing"
+        );
+
+        template.resolve_defaults().unwrap();
+        assert_eq!(template.render(), "Be FRIENDLY. Be brief. there");
+
+        assert_eq!(
+            render(
+                source,
+                json!({"tone": "formal", "brief": false, "user": {"name": "Ada"}})
+            ),
+            "Be FORMAL. Ada"
+        );
+        assert_eq!(render("{{tone | default: 3}}", json!({"tone": null})), "3");
+    }
+
+    #[test]
+    fn test_unclosed_blocks_are_literal() {
+        let template = Template::parse("{{#if a}}x{{else}}y {{/each}}{{b}}");
+        assert_eq!(template.render(), "{{#if a}}x{{else}}y {{/each}}{{b}}");
+        assert_eq!(names(&template), vec!["b"]);
+        assert_eq!(Template::parse(&template.source()), template);
+    }
+
+    #[cfg(feature = "test_utils")]
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn expr() -> impl Strategy<Value = Expr> {
+            let filter = prop_oneof![
+                Just(Filter {
+                    name: "upper".to_string(),
+                    arg: None,
+                }),
+                (0..100u64).prop_map(|count| Filter {
+                    name: "truncate".to_string(),
+                    arg: Some(Value::from(count)),
+                }),
+                "[a-z{}\"\\\\ ]{0,4}".prop_map(|arg| Filter {
+                    name: "suffix".to_string(),
+                    arg: Some(Value::from(arg)),
+                }),
+            ];
+
+            let name = "[a-z_][a-z0-9_]{0,4}".prop_filter("not a keyword", |name| name != "else");
+            let segment = prop_oneof![
+                "[a-z_][a-z0-9_]{0,4}".prop_map(Segment::Key),
+                (0..10usize).prop_map(Segment::Index),
+            ];
+
+            (
+                name,
+                prop::collection::vec(segment, 0..3),
+                prop::collection::vec(filter, 0..2),
+            )
+                .prop_map(|(name, path, filters)| Expr {
+                    name,
+                    path,
+                    filters,
+                })
+        }
+
+        fn leaf() -> impl Strategy<Value = Node> {
+            prop_oneof![
+                "[a-z{}\\\\$ ]{1,8}".prop_map(Node::Text),
+                expr().prop_map(Node::Var),
+            ]
+        }
+
+        fn node() -> impl Strategy<Value = Node> {
+            let nodes = || prop::collection::vec(leaf(), 0..4);
+            prop_oneof![
+                4 => leaf(),
+                1 => (expr(), nodes(), prop::option::of(nodes())).prop_map(
+                    |(condition, then, otherwise)| Node::If {
+                        condition,
+                        then,
+                        otherwise,
+                    }
+                ),
+                1 => (expr(), nodes()).prop_map(|(list, body)| Node::Each { list, body }),
+            ]
+        }
+
+        /// Merges adjacent text the way the parser does.
+        fn normalize(nodes: Vec<Node>) -> Vec<Node> {
+            let mut template = Template::default();
+            for node in nodes {
+                template.push_node(match node {
+                    Node::If {
+                        condition,
+                        then,
+                        otherwise,
+                    } => Node::If {
+                        condition,
+                        then: normalize(then),
+                        otherwise: otherwise.map(normalize),
+                    },
+                    Node::Each { list, body } => Node::Each {
+                        list,
+                        body: normalize(body),
+                    },
+                    node => node,
+                });
+            }
+            template.nodes
+        }
+
+        fn template() -> impl Strategy<Value = Template> {
+            prop::collection::vec(node(), 0..8).prop_map(|nodes| Template {
+                nodes: normalize(nodes),
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn test_source_round_trips(template in template()) {
+                prop_assert_eq!(Template::parse(&template.source()), template);
+            }
+
+            #[test]
+            fn test_values_are_opaque(
+                names in prop::collection::vec("[a-z_][a-z0-9_]{0,4}", 0..6),
+                texts in prop::collection::vec("[a-z{}\\\\$ ]{0,8}", 0..6),
+                value in "[a-z{}\\\\$ ]{0,12}",
+            ) {
+                let mut template = Template::default();
+                let mut expected = String::new();
+                for (name, text) in names.iter().zip(texts.iter()) {
+                    template.push_text(text);
+                    template.nodes.push(Node::Var(Expr {
+                        name: name.clone(),
+                        path: vec![],
+                        filters: vec![],
+                    }));
+                    expected.push_str(text);
+                    expected.push_str(&value);
+                }
+
+                let values: serde_json::Map<_, _> = names
+                    .iter()
+                    .map(|name| (name.clone(), Value::from(value.as_str())))
+                    .collect();
+
+                let mut resolved = template.clone();
+                resolved.resolve(&Value::Object(values)).unwrap();
+                prop_assert!(!resolved.has_variables());
+                prop_assert_eq!(resolved.render(), expected);
+                prop_assert_eq!(Template::parse(&resolved.source()), resolved);
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::PromptError;
+use crate::{PromptError, Variable};
+use serde::Serialize;
+use serde_json::Value;
 use std::collections::HashMap;
 
 //-------------------------------------------------------------------------------------------------
@@ -8,16 +10,47 @@ use std::collections::HashMap;
 pub trait FinalizablePrompt {
     type FinalizedPrompt: FinalizedPrompt;
 
+    /// Returns every unresolved placeholder with its location.
+    fn variables(&self) -> Vec<Variable>;
+
     /// Checks if the prompt has unresolved variables.
-    fn has_unresolved_vars(&self) -> Result<bool, PromptError>;
+    fn has_unresolved_vars(&self) -> Result<bool, PromptError> {
+        Ok(!self.variables().is_empty())
+    }
 
     /// Replaces all occurrences of a given variable with the provided value.
-    fn resolve_var(&mut self, var: &str, value: &str) -> Result<(), PromptError>;
+    fn resolve_var(&mut self, var: &str, value: &str) -> Result<(), PromptError> {
+        self.format(HashMap::from([(var, value)]))
+    }
 
     /// Changes occurences of the given variables to the given values if they exist.
-    fn format(&mut self, map: HashMap<&str, &str>) -> Result<(), PromptError>;
+    fn format(&mut self, map: HashMap<&str, &str>) -> Result<(), PromptError> {
+        let values = map
+            .into_iter()
+            .map(|(var, value)| (var.to_string(), Value::from(value)))
+            .collect();
+
+        self.format_value(&Value::Object(values))
+    }
+
+    /// Changes occurences of the given variables to the given structured values if they exist.
+    ///
+    /// `values` must be a JSON object whose fields are the variables. Unlike `format`, this lets
+    /// templates loop over lists and test values in conditionals.
+    fn format_value(&mut self, values: &Value) -> Result<(), PromptError>;
+
+    /// Changes occurences of the variables to the fields of the given context, which can be any
+    /// value that serializes to a map, such as a struct, a `HashMap` or a `serde_json::Value`.
+    fn format_with<T>(&mut self, context: &T) -> Result<(), PromptError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.format_value(&serde_json::to_value(context)?)
+    }
 
     /// Resolves into its final form.
+    ///
+    /// Fails with `PromptError::UnresolvedVars` listing the placeholders left, if there are any.
     fn finalize(self) -> Result<Self::FinalizedPrompt, PromptError>;
 
     /// Resolves the prompt into a finalized prompt.
@@ -26,12 +59,141 @@ pub trait FinalizablePrompt {
         Self: Sized,
     {
         self.format(map)?;
-        if self.has_unresolved_vars()? {
-            return Err(PromptError::UnresolvedVars);
-        }
+        self.finalize()
+    }
 
+    /// Resolves the prompt into a finalized prompt, taking the variables from the given context.
+    fn resolve_with<T>(mut self, context: &T) -> Result<Self::FinalizedPrompt, PromptError>
+    where
+        Self: Sized,
+        T: Serialize + ?Sized,
+    {
+        self.format_with(context)?;
         self.finalize()
     }
 }
 
 pub trait FinalizedPrompt {}
+
+/// A struct whose fields are the variables of a prompt template.
+///
+/// It is usually derived with `#[derive(PromptTemplate)]` from the `derive` feature, which checks
+/// at compile time that every variable of the template has a matching field and that every field
+/// is used, so resolving cannot fail with `PromptError::UnresolvedVars`.
+pub trait PromptTemplate: Serialize {
+    type Prompt: FinalizablePrompt;
+
+    /// Returns the unresolved template.
+    fn template() -> Self::Prompt;
+
+    /// Resolves the template with the fields of the struct.
+    fn to_prompt(
+        &self,
+    ) -> Result<<Self::Prompt as FinalizablePrompt>::FinalizedPrompt, PromptError> {
+        Self::template().resolve_with(self)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tests
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Role, Tag};
+    use versa_common::PromptTemplate;
+
+    #[derive(Serialize, PromptTemplate)]
+    #[prompt("Write a {{tone | default: \"friendly\"}} note to {{user.name}} about {{topic}}.")]
+    struct Note<'a> {
+        user: User<'a>,
+        topic: &'a str,
+        tone: Option<&'a str>,
+    }
+
+    #[derive(Serialize)]
+    struct User<'a> {
+        name: &'a str,
+    }
+
+    #[derive(Serialize, PromptTemplate)]
+    #[prompt(
+        system = "Answer in {{lang}}.",
+        user = "{{#each questions}}- {{this}}\n{{/each}}"
+    )]
+    struct Quiz {
+        #[serde(rename = "lang")]
+        language: String,
+        questions: Vec<String>,
+    }
+
+    #[derive(Serialize, PromptTemplate)]
+    #[prompt(path = "examples/prompts/review.txt")]
+    struct Review {
+        language: String,
+        focus: Option<String>,
+        code: String,
+    }
+
+    #[derive(Serialize, PromptTemplate)]
+    #[prompt("{{userName}} asked about {{topic}}.{{note | default: \"\"}}")]
+    #[serde(rename_all = "camelCase")]
+    struct Ticket {
+        user_name: String,
+        #[serde(rename(serialize = "topic"))]
+        subject: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        id: u64,
+    }
+
+    #[test]
+    fn test_can_derive_prompt_templates() -> anyhow::Result<()> {
+        let note = Note {
+            user: User { name: "Ada" },
+            topic: "the release",
+            tone: None,
+        };
+        assert_eq!(
+            String::from(note.to_prompt()?),
+            "Write a friendly note to Ada about the release."
+        );
+
+        let quiz = Quiz {
+            language: "French".into(),
+            questions: vec!["Why?".into(), "How?".into()],
+        };
+        let prompt = quiz.to_prompt()?;
+        let messages: Vec<_> = prompt.iter().collect();
+        assert!(matches!(messages[0].1[..], [Tag::Role(Role::System)]));
+        assert_eq!(
+            String::from(prompt),
+            "System: Answer in French.\nUser: - Why?\n- How?\n\nAssistant:"
+        );
+
+        let review = Review {
+            language: "Rust".into(),
+            focus: None,
+            code: "fn main() {}".into(),
+        };
+        assert_eq!(
+            String::from(review.to_prompt()?),
+            "Review the following Rust code:\n\nfn main() {}\n"
+        );
+
+        let ticket = Ticket {
+            user_name: "Ada".into(),
+            subject: "billing".into(),
+            note: None,
+            id: 7,
+        };
+        assert_eq!(
+            String::from(ticket.to_prompt()?),
+            "Ada asked about billing."
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+//! Checks that the `PromptTemplate` derive rejects structs whose serialized keys do not match the
+//! template.
+
+#[test]
+fn test_derive_rejects_mismatched_fields() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,16 @@
+use serde::Serialize;
+use versa_common::PromptTemplate;
+
+#[derive(Serialize, PromptTemplate)]
+#[prompt("Hi {{name}}.")]
+struct Greeting {
+    #[serde(flatten)]
+    user: User,
+}
+
+#[derive(Serialize)]
+struct User {
+    name: String,
+}
+
+fn main() {}
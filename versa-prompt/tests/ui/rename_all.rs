@@ -0,0 +1,11 @@
+use serde::Serialize;
+use versa_common::PromptTemplate;
+
+#[derive(Serialize, PromptTemplate)]
+#[prompt("Hi {{user_name}}.")]
+#[serde(rename_all = "camelCase")]
+struct Greeting {
+    user_name: String,
+}
+
+fn main() {}
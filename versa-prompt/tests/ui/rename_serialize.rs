@@ -0,0 +1,11 @@
+use serde::Serialize;
+use versa_common::PromptTemplate;
+
+#[derive(Serialize, PromptTemplate)]
+#[prompt("Hi {{name}}.")]
+struct Greeting {
+    #[serde(rename(serialize = "fullName"))]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use serde::Serialize;
+use versa_common::PromptTemplate;
+
+#[derive(Serialize, PromptTemplate)]
+#[prompt("Hi {{name}}.")]
+struct Greeting {
+    #[serde(skip)]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use serde::Serialize;
+use versa_common::PromptTemplate;
+
+#[derive(Serialize, PromptTemplate)]
+#[prompt("Hi {{name}}.")]
+struct Greeting {
+    #[serde(skip_serializing)]
+    name: String,
+}
+
+fn main() {}